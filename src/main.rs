@@ -1,43 +1,197 @@
 use bevy::{
+    audio::Volume,
+    input::{
+        InputSystem,
+        keyboard::{Key, KeyboardInput},
+    },
     diagnostic::FrameCount, 
-    math::bounding::{Aabb2d, BoundingCircle, BoundingVolume, IntersectsVolume}, 
+    asset::{RenderAssetUsages, io::file::FileAssetReader},
+    render::{
+        camera::ScalingMode,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+    ecs::system::SystemParam,
+    math::bounding::{Aabb2d, AabbCast2d, Bounded2d, BoundingCircle, BoundingVolume, IntersectsVolume}, 
     prelude::*, 
+    time::Stopwatch,
     window::{PresentMode, WindowTheme}
 };
-use rand::Rng;
-// use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::{bevy_egui::EguiPlugin, quick::WorldInspectorPlugin};
 
 const PADDLE_SIZE: Vec2 = Vec2::new(20.0, 120.0);
 const PADDLE_SPEED: f32 = 500.0;
+const BOOST_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+// 格挡时的移动速度倍率
+const BLOCK_MOVE_FACTOR: f32 = 0.5;
+// 接球模式：最多持球的时间，以及掷球角度的上限（挡板在最高或最低处时）
+const CATCH_HOLD_SECS: f32 = 1.0;
+const MAX_THROW_ANGLE: f32 = std::f32::consts::FRAC_PI_4;
+// 击中挡板边缘时的出射角，击中中心时水平弹回
+const MAX_PADDLE_BOUNCE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+// 回球随机偏转后与水平方向的最大夹角，超过时放弃这次偏转
+const MAX_JITTER_BOUNCE_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
 
 const DASHEDLINE_SIZE: f32 = 20.;
 
-const BALL_STARTING_POSITION: Vec3 = Vec3::new(-610.0, 0.0, 1.0);
 const BALL_SIZE: f32 = 20.;
+// 扫掠检测把球退回接触点时多进入碰撞体的距离，保证随后的重叠检测能命中
+const SWEEP_OVERLAP: f32 = 0.5;
 const BALL_SPEED: f32 = 400.0;
+// 默认的球速上限，再快球会穿过挡板
+const MAX_BALL_SPEED: f32 = BALL_SPEED * 3.0;
 const INITIAL_BALL_DIRECTION: Vec2 = Vec2::new(0.5, -0.5);
+// 球速超过最大球速的这一比例时进入高温状态
+const HOT_BALL_FRACTION: f32 = 0.8;
+const HOT_BALL_COLOR: Color = Color::srgb(1.0, 0.45, 0.2);
+// 旋转每秒衰减的比例，保证弧线的总偏转角有限
+const SPIN_DECAY: f32 = 1.0;
 
 const WALL_THICKNESS: f32 = 1.0;
+const WALL_COLOR: Color = Color::WHITE;
+// 左右墙显示时用较暗的颜色，只作为边界提示
+const SIDE_WALL_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+// 球速越快，上下墙越偏向这个颜色
+const WALL_GLOW_COLOR: Color = Color::srgb(1.0, 0.6, 0.2);
+const WALL_GLOW_RESPONSE: f32 = 4.0; // 光晕跟随球速变化的快慢
 const VERTICAL_WALL_THICKNESS: f32 = 20.0;
-const LEFT_WALL: f32 = -640.;
-const RIGHT_WALL: f32 = 640.;
+// 经典场地的宽度；左右墙的位置由 Arena 按宽高比算出
+const CLASSIC_ARENA_WIDTH: f32 = 1280.;
 const BOTTOM_WALL: f32 = -470.;
 const TOP_WALL: f32 = 470.;
 
 const GAP_BETWEEN_PADDLE_AND_SIDES: f32 = 10.0;
+// 重击（入射球速超过阈值）时挡板后坐的最大距离
+const HARD_HIT_SPEED: f32 = BALL_SPEED * 2.0;
+const MAX_PADDLE_RECOIL: f32 = 8.0;
+const PADDLE_RECOIL_SECS: f32 = 0.2;
+
+// 挡板停在上下墙前的间隙，必须小于球的直径，避免球卡进挡板和墙之间
+const PADDLE_EDGE_MARGIN: f32 = 4.0;
 const GAP_BETWEEN_DASHEDLINESEGMENTS: f32 = 40.0;
+// 背景网格的间距和颜色，要足够暗，不能和球混淆
+const BACKGROUND_GRID_SPACING: f32 = 80.0;
+const BACKGROUND_GRID_COLOR: Color = Color::srgb(0.09, 0.09, 0.12);
+// 暗角贴图的边长，以及从中心起开始变暗的位置（占半场的比例）
+const VIGNETTE_TEXTURE_SIZE: u32 = 64;
+const VIGNETTE_INNER_RADIUS: f32 = 0.55;
+
+const MENU_SELECTED_COLOR: Color = Color::WHITE;
+const MENU_UNSELECTED_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+// 顶部进度条中左右两名玩家的颜色
+const PLAYER_COLORS: [Color; 2] = [Color::srgb(0.3, 0.7, 1.0), Color::srgb(1.0, 0.6, 0.25)];
+const INPUT_ACTIVE_COLOR: Color = Color::srgb(1.0, 0.85, 0.2);
+const INPUT_IDLE_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
 
 const SCOREBOARD_FONT_SIZE: f32 = 150.0;
+// 比分和名字到中线的距离，以中线为基准布局，任何窗口宽度下都居中
+const SCOREBOARD_CENTER_GAP: f32 = 40.0;
 const VICTORY_TEXT_FONT_SIZE: f32 = 150.0;
 const HINT_FONT_SIZE: f32 = 50.0;
+const TITLE_FONT_SIZE: f32 = 100.0;
+const SETTING_FONT_SIZE: f32 = 30.0;
+const BOUNCE_MESSAGE_FONT_SIZE: f32 = 40.0;
+const RALLY_FONT_SIZE: f32 = 30.0;
+
+const BOUNCE_MESSAGE_SECS: f32 = 1.5;
+const ROULETTE_MESSAGE_SECS: f32 = 2.5;
+// 速度箭头的长度等于球在这段时间内走过的距离
+const VELOCITY_ARROW_SECS: f32 = 0.25;
+const GAMEPAD_WARNING_SECS: f32 = 3.0;
+// 摇杆推过这一比例才算移动
+const GAMEPAD_DEADZONE: f32 = 0.3;
+const SCORE_POP_SECS: f32 = 0.25;
+const SCORE_POP_SCALE: f32 = 0.3; // 弹跳时额外放大的比例
+const WALL_MARK_SECS: f32 = 0.6;
+// 撞墙或障碍的声音在这段时间内不重复播放；挡板击球不受限制
+const COLLISION_SOUND_COOLDOWN_SECS: f32 = 0.06;
+// 碰撞声的音高范围，按球速与初始球速之比取值
+const MIN_COLLISION_PITCH: f32 = 0.8;
+const MAX_COLLISION_PITCH: f32 = 1.6;
+
+// 即时回放：回放最近3秒，以半速播放
+const REPLAY_SECONDS: f32 = 3.0;
+const REPLAY_PLAYBACK_SPEED: f32 = 0.5;
+// 进球回放：镜头放大跟随球，回放进球前半秒（半速播放约1秒）
+const GOAL_REPLAY_SECONDS: f32 = 0.5;
+// 限时挑战的初始时间，玩家每得一分延长的时间
+const TIME_ATTACK_SECS: f32 = 60.0;
+const TIME_ATTACK_BONUS_SECS: f32 = 5.0;
+// 决胜球后画面定格的默认时长
+const WIN_FREEZE_SECS: f32 = 0.75;
+// 启动画面的默认时长，其中最后一段用来淡出
+const SPLASH_SECS: f32 = 1.5;
+const SPLASH_FADE_SECS: f32 = 0.5;
+const GOAL_CAMERA_ZOOM: f32 = 0.5;
 
 const TARGET_SCORE: usize = 9;
+// 主菜单中可选的目标分数
+const TARGET_SCORE_PRESETS: [usize; 3] = [5, 9, 11];
+// 淘汰模式中每名玩家的生命数
+const STARTING_HEALTH: u32 = 5;
+// 电脑挡板离目标小于这个距离时开始减速
+const AI_EASE_DISTANCE: f32 = PADDLE_SIZE.y;
+// 球离挡板中心小于这个距离时电脑不动，留出可乘之机
+const AI_DEADZONE: f32 = 10.0;
+const HEALTH_LOST_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+
+const MAX_NAME_LENGTH: usize = 12;
+
+// 挡板可移动范围预设，占完整范围的比例
+const PADDLE_TRAVEL_PRESETS: [f32; 3] = [1.0, 0.75, 0.5];
+
+// 开局热身时长预设（秒），0 表示关闭
+const GRACE_PERIOD_PRESETS: [f32; 3] = [0.0, 5.0, 10.0];
+
+// 混乱模式下场上最多同时存在的分裂球
+const MAX_SPLIT_BALLS: usize = 5;
+
+// 发球后这段时间内可以叫 let 重新发球，每局最多叫几次
+const LET_WINDOW_SECS: f32 = 0.5;
+// 每次发球前球停在发球点倒数 3、2、1 的总时长
+const SERVE_COUNTDOWN_SECS: f32 = 1.5;
+const LETS_PER_GAME: usize = 3;
+
+// 窗口分辨率预设（均为 4:3），场地按窗口高度整体缩放
+const RESOLUTION_PRESETS: [UVec2; 3] = [UVec2::new(1280, 960), UVec2::new(1024, 768), UVec2::new(1600, 1200)];
+// 场地和界面按这个高度设计
+const DESIGN_HEIGHT: f32 = 960.0;
+
+// 帧率上限预设，None 表示不限制（由 VSync 决定）
+const FRAME_CAP_PRESETS: [Option<u32>; 4] = [None, Some(30), Some(60), Some(120)];
+
+// 存档文件，每行一个 key=value
+const SAVE_FILE: &str = "pong_save.txt";
+// 上一局的种子和逐帧输入
+const MATCH_REPLAY_FILE: &str = "pong_replay.txt";
 
 const OBSTACLE_SIZE_RANGE: [Vec2; 2] = [Vec2::new(30.0, 100.0), Vec2::new(30.0, 200.0)];
-const OBSTACLE_COUNT:i32 = 5;
+// 普通难度的障碍数量，其他难度见 Difficulty::obstacle_density
+const OBSTACLE_COUNT: usize = 5;
+// 障碍反弹时保留的法向速度比例，墙和挡板为 1.0（完全弹性）
+const OBSTACLE_RESTITUTION: f32 = 0.9;
+// 传送门放在中间那条不生成随机障碍的区域里，一上一下
+const PORTAL_POSITIONS: [Vec2; 2] = [Vec2::new(-100.0, 320.0), Vec2::new(100.0, -320.0)];
+const PORTAL_RADIUS: f32 = 30.0;
+const PORTAL_COOLDOWN_SECS: f32 = 0.5;
+// 中央旋转障碍：两根交叉的长条
+const SPINNER_ARM_LENGTH: f32 = 100.0;
+const SPINNER_THICKNESS: f32 = 16.0;
+// 道具：每隔一段时间在场上生成一个，球碰到后归最后击球的一方
+const POWER_UP_INTERVAL_SECS: f32 = 10.0;
+const POWER_UP_SIZE: f32 = 30.0;
+const BLACKOUT_SECS: f32 = 3.0;
+// 碰撞粗筛网格的格子边长
+const COLLIDER_GRID_CELL: f32 = 128.0;
 
 fn main() {
-    App::new()
+    let mut app = App::new();
+    app
         .add_plugins((
             DefaultPlugins.set(WindowPlugin {
                 primary_window: Some(Window {
@@ -57,25 +211,108 @@ fn main() {
                 ..default()
             }),
         ))
-        // .add_plugins(EguiPlugin { enable_multipass_for_primary_context: true })
-        // .add_plugins(WorldInspectorPlugin::new())
         .init_state::<GameState>()
         .insert_resource(Winner::default())
         .insert_resource(Score(0, 0))
         .insert_resource(ClearColor(Color::BLACK))
+        .insert_resource(DebugColliders(false))
+        .init_resource::<InputOverlay>()
+        .init_resource::<SpeedHud>()
+        .init_resource::<StateOverlay>()
+        .init_resource::<VelocityArrow>()
+        .init_resource::<LatencyProbe>()
+        .insert_resource(BounceCount(0))
+        .init_resource::<Rally>()
+        .init_resource::<GameMode>()
+        .init_resource::<AudioSettings>()
+        .init_resource::<BallDynamics>()
+        .init_resource::<Modifiers>()
+        .init_resource::<BallExitBehavior>()
+        .init_resource::<GracePeriod>()
+        .init_resource::<CenterSpinner>()
+        .init_resource::<ModifierRoulette>()
+        .init_resource::<Difficulty>()
+        .init_resource::<EndlessMode>()
+        .init_resource::<SinglePlayer>()
+        .init_resource::<MatchSettings>()
+        .init_resource::<PointHistory>()
+        .init_resource::<PrePointState>()
+        .init_resource::<ResumeMatch>()
+        .init_resource::<PreGameOver>()
+        .init_resource::<Health>()
+        .init_resource::<Bracket>()
+        .insert_resource(ObstacleLayout::load())
+        .init_resource::<LastCollisionSound>()
+        .init_resource::<PaddleTravelSettings>()
+        .init_resource::<ServeTimer>()
+        .init_resource::<Lets>()
+        .init_resource::<LetRequest>()
+        .init_resource::<GameRng>()
+        .init_resource::<StressTest>()
+        .init_resource::<ColliderGrid>()
+        .init_resource::<LastTouch>()
+        .init_resource::<HotBall>()
+        .init_resource::<PowerUpSpawner>()
+        .init_resource::<PaddleInput>()
+        .init_resource::<MatchRecording>()
+        .init_resource::<MatchReplay>()
+        .init_resource::<ScorePop>()
+        .init_resource::<MenuCursor>()
+        .init_resource::<Theme>()
+        .init_resource::<Accessibility>()
+        .init_resource::<StreakBonus>()
+        .init_resource::<Series>()
+        .insert_resource(DisplaySettings::load())
+        .insert_resource(Arena::load())
+        // 默认发球位置取决于场地，须在 Arena 之后初始化
+        .init_resource::<Serve>()
+        .insert_resource(VictoryMessages::load())
+        .insert_resource(WinFreeze::load())
+        .insert_resource(SplashTimer::load())
+        .insert_resource(PlayerNames::load())
+        .init_resource::<NameEntry>()
+        .init_resource::<Locale>()
+        .init_resource::<KeyBindings>()
+        .init_resource::<SwapSides>()
+        .init_resource::<ControllerAssignment>()
+        .init_resource::<TournamentMode>()
+        .init_resource::<ReplayBuffer>()
+        .init_resource::<ReplayPlayback>()
+        .insert_resource(SpeedrunClock::default())
+        .insert_resource(TimeAttack::load())
+        .insert_resource(SpeedrunRecord(
+            load_saved("speedrun_best").and_then(|best| best.parse().ok()),
+        ))
         .add_event::<CollisionEvent>()
         .add_event::<ScoreEvent>()
         .init_state::<GameState>()
+        .add_computed_state::<InMatch>()
         .enable_state_scoped_entities::<GameState>()
+        .enable_state_scoped_entities::<InMatch>()
         .add_systems(Startup, setup)
-        .add_systems(OnEnter(GameState::Playing), game_reset)
+        .add_systems(OnEnter(GameState::Splash), display_splash)
+        .add_systems(Update, tick_splash.run_if(in_state(GameState::Splash)))
+        .add_systems(OnEnter(InMatch), (apply_game_mode, assign_ai_paddle, (game_reset, spawn_match_obstacles, announce_roulette_modifier, reset_match_counters, start_speedrun, start_grace_period, spawn_center_spinner, spawn_portals, spawn_health_bars, start_bracket_match), resume_undone_match).chain())
         .add_systems(
             FixedUpdate,
             (
-                apply_velocity,
+                (tick_serve_timer, apply_velocity).chain(),
+                read_paddle_input,
+                call_let,
+                (stress_test_autopilot, move_ai_paddle),
                 move_paddle,
+                carry_held_ball,
+                rotate_center_spinner,
+                (rebuild_collider_grid, sweep_ball, snapshot_match_state).chain(),
                 check_for_collisions,
+                catch_ball,
+                (check_extra_ball_collisions, record_point_history).chain(),
+                teleport_through_portals,
+                spawn_wall_marks,
+                trigger_paddle_recoil,
+                record_replay_frame,
                 play_collision_sound,
+                (count_bounces, count_rally, damage_obstacles),
                 ball_reset,
             ).chain().run_if(in_state(GameState::Playing))
         )
@@ -83,660 +320,7660 @@ fn main() {
             Update,
             (
                 make_window_visible, 
-                update_scoreboard,
+                (update_scoreboard, update_health_bars, apply_locale_font, update_rally_hud, update_serve_countdown),
+                update_point_history_ui,
+                toggle_debug_colliders,
+                draw_collider_gizmos,
+                (toggle_input_overlay, update_input_overlay).chain(),
+                (
+                    (toggle_speed_hud, update_speed_hud).chain(),
+                    (toggle_state_overlay, update_state_overlay).chain(),
+                ),
+                (toggle_velocity_arrow, draw_velocity_arrow.run_if(in_state(InMatch))).chain(),
+                (toggle_latency_probe, update_latency_hud).chain(),
+                expire_bounce_messages,
+                apply_center_line_color,
+                apply_background,
+                apply_vignette,
+                fade_wall_marks,
+                apply_present_mode,
+                (apply_resolution, apply_ui_scale).chain(),
+                animate_paddle_recoil,
+                update_wall_glow,
+                update_player_name_labels,
+                (assign_connected_gamepads, fall_back_from_disconnected_gamepads),
+            )
+        )
+        .add_systems(
+            Update,
+            (
+                (tick_speedrun_clock, tick_time_attack, update_speedrun_hud).chain(),
+                tick_grace_period,
+                quit_to_menu,
+            )
+                .run_if(in_state(GameState::Playing))
+        )
+        .add_systems(Last, limit_frame_rate)
+        .add_systems(Update, start_instant_replay.run_if(in_state(GameState::Playing)))
+        .add_systems(
+            Update,
+            (toggle_stress_test, update_stress_test_hud).chain().run_if(in_state(GameState::Playing))
+        )
+        .add_systems(OnExit(InMatch), stop_stress_test)
+        // 在输入刚更新后记录按键时刻，早于本帧的 FixedUpdate
+        .add_systems(PreUpdate, probe_key_presses.after(InputSystem))
+        .add_systems(
+            FixedUpdate,
+            measure_paddle_latency.after(move_paddle).run_if(in_state(GameState::Playing))
+        )
+        .add_systems(
+            FixedUpdate,
+            (track_last_touch, spawn_power_ups, collect_power_ups)
+                .chain()
+                .after(check_for_collisions)
+                .before(ball_reset)
+                .run_if(in_state(GameState::Playing))
+        )
+        .add_systems(Update, (expire_blackouts, update_hot_ball, update_boost_effects, flicker_ghost_obstacles))
+        .add_systems(FixedUpdate, start_goal_replay.after(ball_reset).run_if(in_state(GameState::Playing)))
+        .add_systems(OnEnter(GameState::InstantReplay), spawn_replay_ghosts)
+        .add_systems(OnExit(GameState::InstantReplay), show_live_entities)
+        .add_systems(Update, play_instant_replay.run_if(in_state(GameState::InstantReplay)))
+        .add_systems(Update, undo_last_point.run_if(in_state(InMatch)))
+        .add_systems(Update, undo_final_point.run_if(in_state(GameState::GameOver)))
+        .add_systems(FixedUpdate, respawn_obstacles.run_if(in_state(GameState::Playing)))
+        .add_systems(
+            Update,
+            toggle_pause.run_if(in_state(GameState::Playing).or(in_state(GameState::Paused)))
+        )
+        .add_systems(OnEnter(GameState::Paused), display_pause_overlay)
+        .add_systems(OnEnter(GameState::WinFreeze), start_win_freeze)
+        .add_systems(Update, tick_win_freeze.run_if(in_state(GameState::WinFreeze)))
+        .add_systems(
+            OnEnter(GameState::GameOver),
+            (
+                remember_pre_game_over,
+                finish_speedrun,
+                finish_time_attack,
+                finish_match_recording,
+                update_series,
+                advance_bracket,
+                display_winner,
+                display_bracket,
+                display_best_rally,
+                play_victory_jingle,
             )
+                .chain()
         )
-        .add_systems(OnEnter(GameState::GameOver), display_winner)
         .add_systems(
             Update,
-            game_over_keyboard.run_if(in_state(GameState::GameOver)
+            (game_over_keyboard, navigate_menu).run_if(in_state(GameState::GameOver)
 ))
-        .run();
+        .add_systems(OnEnter(GameState::Tutorial), display_tutorial)
+        .add_systems(Update, dismiss_tutorial.run_if(in_state(GameState::Tutorial)))
+        .add_systems(OnEnter(GameState::MainMenu), (reset_settings, end_bracket, display_main_menu).chain())
+        .add_systems(
+            Update,
+            (
+                (
+                    main_menu_keyboard,
+                    (wall_visibility_keyboard, vignette_keyboard, arena_keyboard),
+                    match_options_keyboard,
+                    controller_assignment_keyboard,
+                )
+                    .run_if(resource_equals(NameEntry(None))),
+                enter_player_names,
+                update_setting_labels,
+            )
+                .chain()
+                .run_if(in_state(GameState::MainMenu))
+        );
+
+    // 调试工具只在启用 dev 特性时编译进来，发布构建不包含
+    #[cfg(feature = "dev")]
+    app.add_plugins((
+        EguiPlugin { enable_multipass_for_primary_context: true },
+        WorldInspectorPlugin::new(),
+    ))
+    .add_systems(Update, rebuild_world);
+
+    app.run();
 }
 
-//等待渲染，延迟3帧窗口可见
+//等待渲染，延迟3帧窗口可见；之后若窗口意外变为不可见则重新设为可见
 fn make_window_visible(mut window: Single<&mut Window>, frames: Res<FrameCount>){
-    if frames.0 == 3{
+    if frames.0 >= 3 && !window.visible {
         window.visible = true;
     }
 }
 
-#[derive(Component, PartialEq, Eq)]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
 enum PaddleType {
     Left,
     Right,
 }
 
+impl PaddleType {
+    // 所在半场的方向：左 -1，右 1
+    fn side(&self) -> f32 {
+        match self {
+            PaddleType::Left => -1.0,
+            PaddleType::Right => 1.0,
+        }
+    }
+
+    fn opponent(&self) -> PaddleType {
+        match self {
+            PaddleType::Left => PaddleType::Right,
+            PaddleType::Right => PaddleType::Left,
+        }
+    }
+
+    // 挡板所在的固定列
+    fn column_x(&self, arena: &Arena) -> f32 {
+        match self {
+            PaddleType::Left => arena.left() + GAP_BETWEEN_PADDLE_AND_SIDES,
+            PaddleType::Right => arena.right() - GAP_BETWEEN_PADDLE_AND_SIDES,
+        }
+    }
+}
+
 #[derive(Component)]
 struct Paddle;
 
+// 由电脑操控的挡板，单人模式下挂在右侧挡板上
+#[derive(Component)]
+struct AiControlled;
+
+// 挡板当前的竖直速度，由 move_paddle 每帧更新
+#[derive(Component)]
+struct PaddleVelocity(f32);
+
+// 按住格挡键时挡板移动变慢，但回球速度降低
+#[derive(Component, Default)]
+struct Blocking(bool);
+
+// 被接住的球跟着挡板移动，松开格挡键或持球超时后掷出
+#[derive(Component)]
+struct HeldBall {
+    by: PaddleType,
+    offset: f32, // 球心相对挡板中心的竖直偏移
+    speed: f32,  // 掷出时的球速
+    timer: Timer,
+}
+
+// 重击后挡板向后退再缓回原列，只是视觉效果
+#[derive(Component)]
+struct PaddleRecoil {
+    timer: Timer,
+    amount: f32,
+}
+
 #[derive(Component)]
 struct Ball;
 
 #[derive(Component)]
 struct DashedLineSegment;
 
+// 障碍开局时生成、离开比赛时随 StateScoped 清除。布局设置了耐久时可以被撞毁，
+// 设置了重生时间时在原位置重新生成
 #[derive(Component)]
 struct Obstacle;
 
+// 可摧毁障碍的剩余耐久，每被主球撞到一次减一
+#[derive(Component)]
+struct Durability(u32);
+
+// 障碍生成时的位置、尺寸和幽灵概率，重生时原样恢复
+#[derive(Component, Clone, Copy)]
+struct ObstacleOrigin {
+    position: Vec2,
+    size: Vec2,
+    phase_chance: Option<f32>,
+}
+
+// 外框对应的内部黑块，摧毁时一并清除
+#[derive(Component)]
+struct ObstacleInner(Entity);
+
+// 被摧毁的障碍等待重生，计时结束后在 ObstacleOrigin 的位置重新生成
+#[derive(Component)]
+struct RespawnAfter(Timer);
+
+// 固定的障碍布局 (中心, 尺寸)，在存档中用 obstacle_layout=x,y,宽,高;x,y,宽,高 设置，
+// 为空时随机生成障碍。obstacle_durability 设置被撞几次后摧毁，obstacle_respawn_secs
+// 设置摧毁后几秒重生；不设置时障碍不可摧毁、不重生
+#[derive(Resource, Default)]
+struct ObstacleLayout {
+    obstacles: Vec<(Vec2, Vec2)>,
+    durability: Option<u32>,
+    respawn_delay: Option<f32>,
+}
+
+impl ObstacleLayout {
+    fn load() -> Self {
+        let layout = load_saved("obstacle_layout").unwrap_or_default();
+        let obstacles = layout
+            .split(';')
+            .filter(|entry| !entry.trim().is_empty())
+            .filter_map(|entry| {
+                let values: Vec<f32> = entry.split(',').filter_map(|value| value.trim().parse().ok()).collect();
+                match values[..] {
+                    [x, y, width, height] => Some((Vec2::new(x, y), Vec2::new(width, height))),
+                    _ => {
+                        warn!("ignoring malformed obstacle_layout entry {entry:?}");
+                        None
+                    }
+                }
+            })
+            .collect();
+        ObstacleLayout {
+            obstacles,
+            durability: load_saved("obstacle_durability")
+                .and_then(|value| value.parse().ok())
+                .filter(|hits| *hits > 0),
+            respawn_delay: load_saved("obstacle_respawn_secs")
+                .and_then(|value| value.parse().ok())
+                .filter(|secs: &f32| *secs >= 0.0),
+        }
+    }
+}
+
+// 中央旋转障碍的设置和当前角度
+#[derive(Resource, Default)]
+struct CenterSpinner {
+    speed: SpinnerSpeed,
+    angle: f32,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum SpinnerSpeed {
+    #[default]
+    Off,
+    Slow,
+    Fast,
+}
+
+impl SpinnerSpeed {
+    // 每秒转过的弧度，关闭时为 None
+    fn radians_per_sec(&self) -> Option<f32> {
+        match self {
+            SpinnerSpeed::Off => None,
+            SpinnerSpeed::Slow => Some(0.8),
+            SpinnerSpeed::Fast => Some(2.0),
+        }
+    }
+
+    fn label(&self, locale: Locale) -> &'static str {
+        locale.text(match self {
+            SpinnerSpeed::Off => TextKey::Off,
+            SpinnerSpeed::Slow => TextKey::Slow,
+            SpinnerSpeed::Fast => TextKey::Fast,
+        })
+    }
+
+    fn next(&self) -> SpinnerSpeed {
+        match self {
+            SpinnerSpeed::Off => SpinnerSpeed::Slow,
+            SpinnerSpeed::Slow => SpinnerSpeed::Fast,
+            SpinnerSpeed::Fast => SpinnerSpeed::Off,
+        }
+    }
+}
+
+// 球心进入传送门后，带着原来的速度出现在另一个门的位置
+#[derive(Component)]
+struct Portal {
+    id: u32,
+    partner: Vec2,
+}
+
+// 刚传送过的球暂时不会再被传送，避免在出口门里立刻弹回去
+#[derive(Component)]
+struct PortalCooldown(Timer);
+
+// 旋转障碍的一根长条，按 Transform 的旋转做有向包围盒碰撞
+#[derive(Component)]
+struct SpinnerBar {
+    arm_angle: f32,
+}
+
 #[derive(Resource)]
 struct Score(usize, usize);
 
+// 一方的比分数字，以中线为基准向外增长，多位数时不会压到中线
 #[derive(Component)]
-struct ScoreboardUi;
+struct ScoreboardUi(PaddleType);
+
+// 记录上一帧的比分，分数增加时让对应数字弹跳一次
+#[derive(Resource)]
+struct ScorePop {
+    previous: [usize; 2],
+    timers: [Timer; 2],
+}
 
+impl Default for ScorePop {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(SCORE_POP_SECS, TimerMode::Once);
+        timer.tick(timer.duration());
+        ScorePop {
+            previous: [0, 0],
+            timers: [timer.clone(), timer],
+        }
+    }
+}
+
+// 初始状态的 OnEnter 早于 Startup 运行，所以 Splash / MainMenu 只生成 UI；
+// 球和挡板由 setup 在 Startup 中生成，按空格进入 Playing 时必定已经存在
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, States)]
 enum GameState {
     #[default]
+    Splash,        // 启动时显示标题，淡出后进入主菜单
     Playing,
     GameOver, // 存储胜利方
+    MainMenu,
+    InstantReplay, // 暂停比赛，慢放最近几秒
+    Tutorial,      // 首次启动时的操作说明
+    WinFreeze,     // 决胜球后定格片刻，再进入结算
+    Paused,        // Esc 暂停，保留球速和比分
 }
 
-#[derive(Resource, Default)]
-struct Winner(Option<PaddleType>);
+// 一局比赛进行中（包括即时回放和暂停），离开时清理本局实体，进入时重置比赛
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct InMatch;
 
-#[derive(Component)]
-struct VictoryText;
+impl ComputedStates for InMatch {
+    type SourceStates = GameState;
 
-#[derive(Component)]
-struct TextBackground;
+    fn compute(sources: GameState) -> Option<Self> {
+        matches!(
+            sources,
+            GameState::Playing
+                | GameState::InstantReplay
+                | GameState::WinFreeze
+                | GameState::Paused
+        )
+        .then_some(InMatch)
+    }
+}
 
-#[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
+// 定格计时器，时长可在存档中用 win_freeze_secs 修改，0 表示不定格
+#[derive(Resource)]
+struct WinFreeze(Timer);
 
-#[derive(Event, Default)]
-struct CollisionEvent;
+impl WinFreeze {
+    fn load() -> Self {
+        let secs = load_saved("win_freeze_secs")
+            .and_then(|secs| secs.parse::<f32>().ok())
+            .unwrap_or(WIN_FREEZE_SECS);
+        WinFreeze(Timer::from_seconds(secs.max(0.0), TimerMode::Once))
+    }
+}
 
-#[derive(Event, Default)]
-enum ScoreEvent {
-    #[default]
-    Player1Scored,
-    Player2Scored,
+// 启动画面计时器，时长可在存档中用 splash_secs 修改，0 表示跳过
+#[derive(Resource)]
+struct SplashTimer(Timer);
+
+impl SplashTimer {
+    fn load() -> Self {
+        let secs = load_saved("splash_secs")
+            .and_then(|secs| secs.parse::<f32>().ok())
+            .unwrap_or(SPLASH_SECS);
+        SplashTimer(Timer::from_seconds(secs.max(0.0), TimerMode::Once))
+    }
 }
 
-#[derive(Resource, Deref)]
-struct CollisionSound(Handle<AudioSource>);
+// 启动画面的遮罩和标题，淡出时一起变透明
+#[derive(Component)]
+struct SplashUi;
 
-#[derive(Resource, Deref)]
-struct ScoreSound(Handle<AudioSource>);
+struct ReplayFrame {
+    ball: Vec3,
+    left_paddle: Vec3,
+    right_paddle: Vec3,
+}
 
-#[derive(Component, Default)]
-struct Collider;
+// 最近几秒的球和挡板位置，每个 FixedUpdate 记录一帧
+#[derive(Resource, Default)]
+struct ReplayBuffer(VecDeque<ReplayFrame>);
 
-#[derive(Component)]
-#[require(Sprite, Transform, Collider)]
-struct Wall;
+#[derive(Resource, Default)]
+struct ReplayPlayback {
+    cursor: f32,       // 当前播放到的帧
+    goal_camera: bool, // 进球回放：只播最后一段，镜头跟随球
+}
 
-#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum WallType {
-    Left,
-    Right,
-    Bottom,
-    Top,
+#[derive(Component)]
+enum ReplayGhost {
+    Ball,
+    Paddle(PaddleType),
 }
 
-enum WallLocation {
-    Left,
-    Right,
-    Bottom,
-    Top,
+#[derive(Resource, Default)]
+struct Winner(Option<PaddleType>);
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum GameMode {
+    #[default]
+    Standard,
+    Speedrun, // 计时模式：记录达到目标分数所用时间
+    Classic,  // 经典模式：无障碍、接球不加速
+    Chaos,    // 混乱模式：球撞上下墙时可能分裂
+    Capture,  // 接球模式：按住格挡键接住球，松开后掷出
+    TimeAttack, // 限时挑战：倒计时结束前对电脑拿到目标分数
+    Elimination, // 淘汰模式：每失一球扣一条命，生命耗尽者输
 }
 
-impl WallLocation {
-    // 墙体中心位置
-    fn position(&self) -> Vec2 {
+impl GameMode {
+    const ALL: [GameMode; 7] = [
+        GameMode::Standard,
+        GameMode::Speedrun,
+        GameMode::Classic,
+        GameMode::Chaos,
+        GameMode::Capture,
+        GameMode::TimeAttack,
+        GameMode::Elimination,
+    ];
+
+    fn label(&self, locale: Locale) -> &'static str {
+        locale.text(match self {
+            GameMode::Standard => TextKey::StandardMode,
+            GameMode::Speedrun => TextKey::SpeedrunMode,
+            GameMode::Classic => TextKey::ClassicMode,
+            GameMode::Chaos => TextKey::ChaosMode,
+            GameMode::Capture => TextKey::CaptureMode,
+            GameMode::TimeAttack => TextKey::TimeAttackMode,
+            GameMode::Elimination => TextKey::EliminationMode,
+        })
+    }
+
+    fn description(&self) -> TextKey {
         match self {
-            WallLocation::Left => Vec2::new(LEFT_WALL, 0.),
-            WallLocation::Right => Vec2::new(RIGHT_WALL, 0.),
-            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL),
-            WallLocation::Top => Vec2::new(0., TOP_WALL),
+            GameMode::Standard => TextKey::StandardDescription,
+            GameMode::Speedrun => TextKey::SpeedrunDescription,
+            GameMode::Classic => TextKey::ClassicDescription,
+            GameMode::Chaos => TextKey::ChaosDescription,
+            GameMode::Capture => TextKey::CaptureDescription,
+            GameMode::TimeAttack => TextKey::TimeAttackDescription,
+            GameMode::Elimination => TextKey::EliminationDescription,
         }
     }
 
-    // 墙面尺寸
-    fn size(&self) -> Vec2 {
-        let arena_height = TOP_WALL - BOTTOM_WALL;
-        let arena_width = RIGHT_WALL - LEFT_WALL;
-
-        assert!(arena_height > 0.0);
-        assert!(arena_width > 0.0);
+    fn next(&self) -> GameMode {
+        match self {
+            GameMode::Standard => GameMode::Speedrun,
+            GameMode::Speedrun => GameMode::Classic,
+            GameMode::Classic => GameMode::Chaos,
+            GameMode::Chaos => GameMode::Capture,
+            GameMode::Capture => GameMode::TimeAttack,
+            GameMode::TimeAttack => GameMode::Elimination,
+            GameMode::Elimination => GameMode::Standard,
+        }
+    }
 
+    fn previous(&self) -> GameMode {
         match self {
-            WallLocation::Left | WallLocation::Right => {
-                Vec2::new(WALL_THICKNESS, arena_height + VERTICAL_WALL_THICKNESS)
-            }
-            WallLocation::Bottom | WallLocation::Top => {
-                Vec2::new(arena_width + WALL_THICKNESS, VERTICAL_WALL_THICKNESS)
-            }
+            GameMode::Standard => GameMode::Elimination,
+            GameMode::Speedrun => GameMode::Standard,
+            GameMode::Classic => GameMode::Speedrun,
+            GameMode::Chaos => GameMode::Classic,
+            GameMode::Capture => GameMode::Chaos,
+            GameMode::TimeAttack => GameMode::Capture,
+            GameMode::Elimination => GameMode::TimeAttack,
         }
     }
 }
 
-impl Wall {
-    fn new(location: WallLocation) -> (Wall, WallType, Sprite, Transform) {
-        // 上下墙白色，左右墙不可见
-        let color = match location{
-            WallLocation::Left | WallLocation::Right => {
-                Color::NONE
-            }
-            WallLocation::Bottom | WallLocation::Top => {
-                Color::WHITE
-            }
-        };
-        let walltype = match location{
-            WallLocation::Left => {
-                WallType::Left
-            }
-            WallLocation::Right => {
-                WallType::Right
-            }
-            WallLocation::Top => {
-                WallType::Top
-            }
-            WallLocation::Bottom => {
-                WallType::Bottom
-            }
-        };
-        (
-            Wall,
-            walltype,
-            Sprite::from_color(color, Vec2::ONE),
-            Transform {
-                translation: location.position().extend(0.0),
-                scale: location.size().extend(1.0),
-                ..default()
-            },
-        )
+// 玩家配置的球体参数
+#[derive(Resource, Clone)]
+struct BallDynamics {
+    paddle_speed_up: f32, // 每次接球后的球速倍率
+    // 动量传递：接球时把挡板速度的这一比例叠加到球速上，启用时取代固定倍率
+    momentum_transfer: Option<f32>,
+    min_speed: f32,
+    max_speed: f32,
+    // 弧线强度：接球时挡板速度乘以该系数得到球的旋转角速度（弧度/秒）
+    curve_strength: f32,
+    block_dampening: f32, // 格挡接球时的球速倍率
+    wall_damping: f32,    // 撞上下墙时竖直速度的保留比例，1.0 为不衰减
+    // 挡板回球时随机偏转的最大角度（弧度），0 为不偏转
+    reflection_jitter: f32,
+}
+
+impl Default for BallDynamics {
+    fn default() -> Self {
+        BallDynamics {
+            paddle_speed_up: 1.1,
+            momentum_transfer: None,
+            min_speed: BALL_SPEED * 0.75,
+            max_speed: MAX_BALL_SPEED,
+            curve_strength: 0.001,
+            block_dampening: 0.7,
+            wall_damping: 1.0,
+            reflection_jitter: 0.0,
+        }
     }
 }
 
-fn setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
-    // Camera
-    commands.spawn(Camera2d);
+// 本局实际生效的修正项，进入 Playing 时由 GameMode 和玩家配置推导
+#[derive(Resource, Default)]
+struct Modifiers {
+    obstacles: bool,
+    obstacle_density: ObstacleDensity,
+    serve_must_clear_net: bool, // 发出的球越过中线之前不能得分
+    power_ups: bool,
+    ball_split_chance: f32, // 球撞上下墙时分裂的概率
+    phase_chance: f32,      // 大于 0 时生成一个幽灵障碍，球每次接触它时穿过的概率
+    portals: bool,
+    catch: bool,   // 按住格挡键接球
+    endless: bool, // 达到目标分数后不结束比赛
+    target_score: usize,
+    win_by_two: bool, // 达到目标分数后还须领先两分
+    ai_opponent: bool, // 右挡板由电脑控制
+    elimination: bool, // 失球扣生命，生命耗尽时比赛结束
+    ball: BallDynamics,
+}
 
-    // Sound
-    let ball_collision_sound = asset_server.load("sounds/pong_collision.ogg");
-    commands.insert_resource(CollisionSound(ball_collision_sound));
+// 无尽模式：达到目标分数后比赛继续，只能按 End 手动回到主菜单
+#[derive(Resource, Default)]
+struct EndlessMode(bool);
 
-    let score_sound = asset_server.load("sounds/score.ogg");
-    commands.insert_resource(ScoreSound(score_sound));
+// 单人模式：右挡板由电脑控制
+#[derive(Resource, Default)]
+struct SinglePlayer(bool);
 
-    // Paddle 1
-    commands.spawn((
-        Sprite::from_color(Color::WHITE, Vec2::ONE),
-        Transform {
-            translation: Vec3::new(LEFT_WALL + GAP_BETWEEN_PADDLE_AND_SIDES, 0.0, 0.0),
-            scale: PADDLE_SIZE.extend(1.0),
-            ..default()
-        },
-        Paddle,
-        PaddleType::Left,
-        Collider,
-    ));
+// 赛前在主菜单选择的比赛规则，进入比赛时复制到 Modifiers
+#[derive(Resource)]
+struct MatchSettings {
+    target_score: usize,
+    win_by_two: bool,
+    serve_must_clear_net: bool, // 发球越过中线之前不能得分，默认关闭
+}
 
-    // Paddle 2
-    commands.spawn((
-        Sprite::from_color(Color::WHITE, Vec2::ONE),
-        Transform {
-            translation: Vec3::new(RIGHT_WALL - GAP_BETWEEN_PADDLE_AND_SIDES, 0.0, 0.0),
-            scale: PADDLE_SIZE.extend(1.0),
-            ..default()
-        },
-        Paddle,
-        PaddleType::Right,
-        Collider,
-    ));
+impl Default for MatchSettings {
+    fn default() -> Self {
+        MatchSettings {
+            target_score: TARGET_SCORE,
+            win_by_two: false,
+            serve_must_clear_net: false,
+        }
+    }
+}
 
-    // Walls
-    commands.spawn(Wall::new(WallLocation::Left));
-    commands.spawn(Wall::new(WallLocation::Right));
-    commands.spawn(Wall::new(WallLocation::Bottom));
-    commands.spawn(Wall::new(WallLocation::Top));
+impl MatchSettings {
+    fn next_target_score(&self) -> usize {
+        let index = TARGET_SCORE_PRESETS
+            .iter()
+            .position(|preset| *preset == self.target_score)
+            .unwrap_or(0);
+        TARGET_SCORE_PRESETS[(index + 1) % TARGET_SCORE_PRESETS.len()]
+    }
 
-    // Ball
-    commands.spawn((
-        Sprite::from_color(Color::WHITE, Vec2::ONE),
-        Transform {
-            translation: BALL_STARTING_POSITION,
-            scale: Vec3::new(BALL_SIZE, BALL_SIZE, 1.0),
-            ..default()
-        },
-        Ball,
-        Velocity(INITIAL_BALL_DIRECTION.normalize() * BALL_SPEED),
-    ));
+    fn previous_target_score(&self) -> usize {
+        let index = TARGET_SCORE_PRESETS
+            .iter()
+            .position(|preset| *preset == self.target_score)
+            .unwrap_or(0);
+        TARGET_SCORE_PRESETS[(index + TARGET_SCORE_PRESETS.len() - 1) % TARGET_SCORE_PRESETS.len()]
+    }
+}
 
-    // DashedLineSegment
-    let center_line_start = Vec3::new(0.0, TOP_WALL, 0.0);
-    let center_line_end = Vec3::new(0.0, BOTTOM_WALL, 0.0);
-    let total_length = center_line_start.distance(center_line_end);
-    let mut offset = 10.0;
-    while offset < total_length{
-        let position = center_line_start + Vec3::new(0.0, -offset, 0.0);
-        commands.spawn((
-            Mesh2d(meshes.add(Rectangle::new(DASHEDLINE_SIZE, DASHEDLINE_SIZE))),
-            MeshMaterial2d(materials.add(Color::WHITE)),
-            Transform::from_translation(position)
-                .with_scale(Vec3::ONE),
-            DashedLineSegment,
-        ));
-        offset += GAP_BETWEEN_DASHEDLINESEGMENTS;
+// 难度越高，场上的障碍越多、越大
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    fn obstacle_density(&self) -> ObstacleDensity {
+        match self {
+            Difficulty::Easy => ObstacleDensity { count: 3, size_scale: 0.8 },
+            Difficulty::Normal => ObstacleDensity { count: OBSTACLE_COUNT, size_scale: 1.0 },
+            Difficulty::Hard => ObstacleDensity { count: 8, size_scale: 1.2 },
+        }
     }
 
-    // Scoreboard
-    let scoreboard_font = asset_server.load("fonts/Bit3.ttf");
-    commands.spawn((
-        Text::new(""),
-        ScoreboardUi,
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(20.0),
-            left: Val::Px(520.0),
-            ..default()
-        },
-        children![(
-            TextSpan::default(),
-            TextFont {
-                font: scoreboard_font.clone(),
-                font_size: SCOREBOARD_FONT_SIZE,
-                ..default()
-            },
-            TextColor(Color::WHITE),
-        )],
-    ));
-    commands.spawn((
-        Text::new(""),
-        ScoreboardUi,
-        Node {
-            position_type: PositionType::Absolute,
-            top: Val::Px(20.0),
-            right: Val::Px(510.0),
-            ..default()
-        },
-        children![(
-            TextSpan::default(),
-            TextFont {
-                font: scoreboard_font.clone(),
-                font_size: SCOREBOARD_FONT_SIZE,
-                ..default()
-            },
-            TextColor(Color::WHITE),
-        )],
-    ));
+    // 电脑挡板的输入每秒最多变化多少，越大转向越快
+    fn ai_response(&self) -> f32 {
+        match self {
+            Difficulty::Easy => 2.5,
+            Difficulty::Normal => 4.0,
+            Difficulty::Hard => 8.0,
+        }
+    }
+
+    fn label(&self, locale: Locale) -> &'static str {
+        locale.text(match self {
+            Difficulty::Easy => TextKey::Easy,
+            Difficulty::Normal => TextKey::Normal,
+            Difficulty::Hard => TextKey::Hard,
+        })
+    }
 
-    spawn_obstacles(commands);
+    fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
 }
 
-fn update_scoreboard(
-    score: Res<Score>,
-    mut scoreboards: Query<Entity, (With<ScoreboardUi>, With<Text>, Without<VictoryText>)>,
-    mut writer: TextUiWriter,
-) {
-    let entities = scoreboards.iter_mut().collect::<Vec<_>>();
-    if entities.len() == 2 {
-        *writer.text(entities[0], 1) = score.0.to_string();
-        *writer.text(entities[1], 1) = score.1.to_string();
+impl Modifiers {
+    // 调试面板中列出的生效项
+    fn active_labels(&self, locale: Locale) -> Vec<String> {
+        let flags = [
+            (self.obstacles, TextKey::Obstacles),
+            (self.serve_must_clear_net, TextKey::ServeOverNet),
+            (self.power_ups, TextKey::PowerUps),
+            (self.portals, TextKey::Portals),
+            (self.catch, TextKey::Catch),
+            (self.endless, TextKey::EndlessMode),
+            (self.ai_opponent, TextKey::Ai),
+            (self.elimination, TextKey::EliminationMode),
+        ];
+        let mut labels: Vec<String> =
+            flags.iter().filter(|(enabled, _)| *enabled).map(|(_, key)| locale.text(*key).to_string()).collect();
+        if self.ball_split_chance > 0.0 {
+            labels.push(format!("{} {:.0}%", locale.text(TextKey::Split), self.ball_split_chance * 100.0));
+        }
+        if self.phase_chance > 0.0 {
+            labels.push(format!("{} {:.0}%", locale.text(TextKey::Ghost), self.phase_chance * 100.0));
+        }
+        labels
     }
 }
 
-fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time: Res<Time>) {
-    for (mut transform, velocity) in &mut query {
-        transform.translation.x += velocity.x * time.delta_secs();
-        transform.translation.y += velocity.y * time.delta_secs();
+// 障碍的数量和尺寸倍率，放不下时宁可少放也不破坏间距要求
+#[derive(Clone, Copy)]
+struct ObstacleDensity {
+    count: usize,
+    size_scale: f32,
+}
+
+impl Default for ObstacleDensity {
+    fn default() -> Self {
+        Difficulty::default().obstacle_density()
     }
 }
 
-fn move_paddle(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&mut Transform, &PaddleType), With<Paddle>>,
-    time: Res<Time>,
-) {
-    let top_bound = TOP_WALL - WALL_THICKNESS / 2.0 - PADDLE_SIZE.y / 2.0;
-    let bottom_bound = BOTTOM_WALL + WALL_THICKNESS / 2.0 + PADDLE_SIZE.y / 2.0;
-    let mut directions = (0.0, 0.0);
-    let mut accelerate_factors = (1.0, 1.0);
+// 随机修正可以抽到的效果，叠加在当前模式的修正项之上
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Modifier {
+    SplitBall,
+    GhostObstacle,
+    Portals,
+    PowerUps,
+    SuperCurve,
+}
+
+impl Modifier {
+    const ALL: [Modifier; 5] = [
+        Modifier::SplitBall,
+        Modifier::GhostObstacle,
+        Modifier::Portals,
+        Modifier::PowerUps,
+        Modifier::SuperCurve,
+    ];
+
+    fn label(&self, locale: Locale) -> &'static str {
+        locale.text(match self {
+            Modifier::SplitBall => TextKey::SplitBall,
+            Modifier::GhostObstacle => TextKey::GhostObstacle,
+            Modifier::Portals => TextKey::Portals,
+            Modifier::PowerUps => TextKey::PowerUps,
+            Modifier::SuperCurve => TextKey::SuperCurve,
+        })
+    }
 
-    if keyboard_input.pressed(KeyCode::KeyW) {
-        directions.0 += 1.0;
+    fn apply(&self, modifiers: &mut Modifiers) {
+        match self {
+            Modifier::SplitBall => modifiers.ball_split_chance = modifiers.ball_split_chance.max(0.25),
+            // 幽灵障碍是障碍之一，经典模式下也要生成障碍
+            Modifier::GhostObstacle => {
+                modifiers.obstacles = true;
+                modifiers.phase_chance = modifiers.phase_chance.max(0.5);
+            }
+            Modifier::Portals => modifiers.portals = true,
+            Modifier::PowerUps => modifiers.power_ups = true,
+            Modifier::SuperCurve => {
+                let curve = BallDynamics::default().curve_strength;
+                modifiers.ball.curve_strength = modifiers.ball.curve_strength.max(curve) * 3.0;
+            }
+        }
     }
-    if keyboard_input.pressed(KeyCode::KeyS) {
-        directions.0 -= 1.0;
+}
+
+// 每局开始时用比赛随机数抽取一个修正项，启用列表为空时关闭
+#[derive(Resource, Default)]
+struct ModifierRoulette {
+    enabled_modifiers: Vec<Modifier>,
+    chosen: Option<Modifier>,
+}
+
+impl ModifierRoulette {
+    fn enabled(&self) -> bool {
+        !self.enabled_modifiers.is_empty()
     }
-    if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        accelerate_factors.0 += 1.0;
+
+    fn toggle(&mut self) {
+        self.enabled_modifiers = if self.enabled() { Vec::new() } else { Modifier::ALL.to_vec() };
     }
 
-    if keyboard_input.pressed(KeyCode::ArrowUp) {
-        directions.1 += 1.0;
+    fn spin(&mut self, rng: &mut GameRng) -> Option<Modifier> {
+        self.chosen = self.enabled().then(|| {
+            self.enabled_modifiers[rng.random_range(0..self.enabled_modifiers.len())]
+        });
+        self.chosen
     }
-    if keyboard_input.pressed(KeyCode::ArrowDown) {
-        directions.1 -= 1.0;
+}
+
+// 比赛用的随机数，每局开始时用记录下来的种子重新播种，保证可以完整复现
+#[derive(Resource, Deref, DerefMut)]
+struct GameRng(StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(StdRng::seed_from_u64(0))
     }
-    if keyboard_input.pressed(KeyCode::NumpadEnter) {
-        accelerate_factors.1 += 1.0;
+}
+
+// 一个挡板在一个固定帧内的操作
+#[derive(Clone, Copy, Default)]
+struct PaddleCommand {
+    direction: f32, // 1 向上，-1 向下，0 不动
+    boost: bool,
+    block: bool,
+    target: Option<f32>, // 鼠标控制时挡板追向的高度，优先于 direction
+}
+
+impl PaddleCommand {
+    fn encode(&self) -> String {
+        let direction = match self.direction {
+            d if d > 0.0 => '+',
+            d if d < 0.0 => '-',
+            _ => '0',
+        };
+        let target = self.target.map(|target| format!("@{target}")).unwrap_or_default();
+        format!("{direction}{}{}{target}", u8::from(self.boost), u8::from(self.block))
     }
-    for (mut paddle_transform, paddle_type) in query.iter_mut(){
-        let (direction, accelerate_fact) = match paddle_type {
-            PaddleType::Left => (directions.0, accelerate_factors.0),
-            PaddleType::Right => (directions.1, accelerate_factors.1)
+
+    fn decode(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        let direction = match chars.next()? {
+            '+' => 1.0,
+            '-' => -1.0,
+            _ => 0.0,
         };
-        let new_paddle_position = paddle_transform.translation.y + direction * PADDLE_SPEED * accelerate_fact * time.delta_secs();
-        paddle_transform.translation.y = new_paddle_position.clamp(bottom_bound, top_bound);
+        let boost = chars.next()? == '1';
+        let block = chars.next()? == '1';
+        let target = match chars.as_str().strip_prefix('@') {
+            Some(target) => Some(target.parse().ok()?),
+            None => None,
+        };
+        Some(PaddleCommand { direction, boost, block, target })
     }
 }
 
-fn check_for_collisions(
-    mut score: ResMut<Score>,
-    mut winner: ResMut<Winner>,
-    mut next_state: ResMut<NextState<GameState>>,
-    ball_query: Single<(&mut Velocity, &Transform), With<Ball>>,
-    collider_query: Query<(&Transform, Option<&WallType>, Option<&Paddle>), With<Collider>>,
-    mut collision_events: EventWriter<CollisionEvent>,
-    mut score_events: EventWriter<ScoreEvent>,
-) {
-    let (mut ball_velocity, ball_transform) = ball_query.into_inner();
+// move_paddle 读取的输入，来自键盘或比赛回放
+#[derive(Resource, Default)]
+struct PaddleInput {
+    left: PaddleCommand,
+    right: PaddleCommand,
+}
+
+// 当前这局的种子和逐帧输入记录
+#[derive(Resource, Default)]
+struct MatchRecording {
+    seed: u64,
+    mode: GameMode,
+    inputs: Vec<[PaddleCommand; 2]>,
+    lets: Vec<usize>, // 叫 let 的帧序号
+}
 
-    for (collider_transform, maybe_wall_type, maybe_paddle) in &collider_query {
-        let collision = ball_collision(
-            BoundingCircle::new(ball_transform.translation.truncate(), BALL_SIZE / 2.),
-            Aabb2d::new(
-                collider_transform.translation.truncate(),
-                collider_transform.scale.truncate() / 2.,
+impl MatchRecording {
+    fn save(&self) {
+        let mut lines = vec![
+            format!("seed={}", self.seed),
+            format!("mode={}", self.mode.label(Locale::English)),
+            format!(
+                "lets={}",
+                self.lets.iter().map(usize::to_string).collect::<Vec<_>>().join(",")
             ),
+        ];
+        lines.extend(
+            self.inputs
+                .iter()
+                .map(|[left, right]| format!("{} {}", left.encode(), right.encode())),
         );
+        if let Err(err) = std::fs::write(MATCH_REPLAY_FILE, lines.join("\n")) {
+            warn!("failed to write {MATCH_REPLAY_FILE}: {err}");
+        }
+    }
 
-        if let Some(collision) = collision {
-            if let Some(wall_type) = maybe_wall_type {
-                match wall_type {
-                    WallType::Right => {
-                        score.0 += 1;
-                        score_events.write(ScoreEvent::Player1Scored);
-                        if score.0 >= TARGET_SCORE {
-                            winner.0 = Some(PaddleType::Left);
-                            next_state.set(GameState::GameOver);
-                        }
-                        continue;
-                    }
-                    WallType::Left => {
-                        score.1 += 1;
-                        score_events.write(ScoreEvent::Player2Scored);
-                        if score.1 >= TARGET_SCORE {
-                            winner.0 = Some(PaddleType::Right);
-                            next_state.set(GameState::GameOver);
-                        }
-                        continue;
-                    }
-                    WallType::Top | WallType::Bottom => {collision_events.write_default();}
+    fn load() -> Option<Self> {
+        let contents = std::fs::read_to_string(MATCH_REPLAY_FILE).ok()?;
+        let mut lines = contents.lines();
+        let seed = lines.next()?.strip_prefix("seed=")?.parse().ok()?;
+        let mode_label = lines.next()?.strip_prefix("mode=")?;
+        let mode = GameMode::ALL.into_iter().find(|mode| mode.label(Locale::English) == mode_label)?;
+        let lets = lines
+            .next()?
+            .strip_prefix("lets=")?
+            .split(',')
+            .filter(|tick| !tick.is_empty())
+            .map(|tick| tick.parse().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let inputs = lines
+            .map(|line| {
+                let (left, right) = line.split_once(' ')?;
+                Some([PaddleCommand::decode(left)?, PaddleCommand::decode(right)?])
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(MatchRecording { seed, mode, inputs, lets })
+    }
+}
+
+// 回放上一局时由记录驱动挡板，而不是键盘
+#[derive(Resource, Default)]
+struct MatchReplay {
+    active: bool,
+    cursor: usize,
+}
+
+// 压力测试：生成大量额外的球和障碍，两侧挡板自动跟球，用于分析碰撞检测的开销
+#[derive(Resource)]
+struct StressTest {
+    active: bool,
+    ball_count: usize,
+    obstacle_count: usize,
+    average_frame_secs: f32,
+}
+
+impl Default for StressTest {
+    fn default() -> Self {
+        StressTest {
+            active: false,
+            ball_count: 200,
+            obstacle_count: 100,
+            average_frame_secs: 0.0,
+        }
+    }
+}
+
+// 压力测试生成的实体，关闭时一并销毁
+#[derive(Component)]
+struct StressTestEntity;
+
+#[derive(Component)]
+struct StressTestHud;
+
+// 均匀网格粗筛：每个碰撞体登记到包围盒覆盖的所有格子，球只与附近格子里的碰撞体做精确检测
+#[derive(Resource, Default)]
+struct ColliderGrid {
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    entities: Vec<Entity>,
+}
+
+impl ColliderGrid {
+    fn cell_range(bounding_box: &Aabb2d) -> (IVec2, IVec2) {
+        let min = (bounding_box.min / COLLIDER_GRID_CELL).floor().as_ivec2();
+        let max = (bounding_box.max / COLLIDER_GRID_CELL).floor().as_ivec2();
+        (min, max)
+    }
+
+    fn rebuild(&mut self, colliders: impl Iterator<Item = (Entity, Aabb2d)>) {
+        self.cells.clear();
+        self.entities.clear();
+        for (entity, bounding_box) in colliders {
+            let index = self.entities.len();
+            self.entities.push(entity);
+            let (min, max) = Self::cell_range(&bounding_box);
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    self.cells.entry((x, y)).or_default().push(index);
                 }
-            } else{
-                collision_events.write_default();
             }
+        }
+    }
 
-            // 每次成功接球后，球速加到1.1倍
-            if maybe_paddle.is_some(){
-                ball_velocity.x *= 1.1;
-                ball_velocity.y *= 1.1;
+    // 按登记顺序返回，与直接遍历查询时的顺序一致
+    fn nearby(&self, bounding_box: Aabb2d) -> Vec<Entity> {
+        let (min, max) = Self::cell_range(&bounding_box);
+        let mut indices = Vec::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    indices.extend_from_slice(cell);
+                }
             }
-            
-            let mut reflect_x = false;
-            let mut reflect_y = false;
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().map(|index| self.entities[index]).collect()
+    }
+}
 
-            match collision {
-                Collision::Left => reflect_x = ball_velocity.x > 0.0,
-                Collision::Right => reflect_x = ball_velocity.x < 0.0,
-                Collision::Top => reflect_y = ball_velocity.y < 0.0,
-                Collision::Bottom => reflect_y = ball_velocity.y > 0.0,
-            }
+// 多球时额外的球到达球门后的处理方式；主球总是计分并重新发球
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum BallExitBehavior {
+    #[default]
+    Reset,   // 回到中场继续，不计分
+    Despawn, // 消失并为对方计分
+}
 
-            if reflect_x {
-                ball_velocity.x = -ball_velocity.x;
-            }
-            if reflect_y {
-                ball_velocity.y = -ball_velocity.y;
-            }
+// 混乱模式中分裂出的球，得分后全部清除
+#[derive(Component)]
+struct SplitBall;
+
+// 只参与反弹、不计分的额外的球
+#[derive(Component)]
+struct ExtraBall;
+
+// 当前这一球的发球方
+#[derive(Resource)]
+struct Serve {
+    side: f32, // 发球所在半场：左 -1，右 1
+    crossed_net: bool,
+    // 发球时球的位置和速度，叫 let 时按原样重发
+    position: Vec3,
+    velocity: Vec2,
+    elapsed: f32, // 发球后经过的时间
+}
+
+impl Serve {
+    fn new(position: Vec3, velocity: Vec2) -> Self {
+        Serve {
+            side: position.x.signum(),
+            crossed_net: false,
+            position,
+            velocity,
+            elapsed: 0.0,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
-enum Collision {
-    Left,
-    Right,
-    Top,
-    Bottom,
+impl FromWorld for Serve {
+    fn from_world(world: &mut World) -> Self {
+        let position = serve_position(PaddleType::Right, world.resource::<Arena>());
+        Serve::new(position.extend(1.0), INITIAL_BALL_DIRECTION.normalize() * BALL_SPEED)
+    }
 }
 
-fn ball_collision(ball: BoundingCircle, bounding_box: Aabb2d) -> Option<Collision> {
-    if !ball.intersects(&bounding_box) {
-        return None;
+// 发球倒计时，结束前球不动，挡板照常移动
+#[derive(Resource)]
+struct ServeTimer(Timer);
+
+impl Default for ServeTimer {
+    // 比赛开始前不倒数
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(SERVE_COUNTDOWN_SECS, TimerMode::Once);
+        timer.tick(timer.duration());
+        ServeTimer(timer)
     }
+}
 
-    let closest = bounding_box.closest_point(ball.center());
-    let offset = ball.center() - closest;
-    let side = if offset.x.abs() > offset.y.abs() {
-        if offset.x < 0. {
-            Collision::Left
-        } else {
-            Collision::Right
-        }
-    } else if offset.y > 0. {
-        Collision::Top
-    } else {
-        Collision::Bottom
-    };
+impl ServeTimer {
+    fn counting_down(&self) -> bool {
+        !self.0.finished()
+    }
+}
 
-    Some(side)
+// 挡板中心能到达的范围，按中线上下分别取完整范围的比例
+#[derive(Clone, Copy, PartialEq)]
+struct PaddleTravel {
+    top_fraction: f32,
+    bottom_fraction: f32,
 }
 
-fn play_collision_sound(
-    mut commands: Commands,
-    mut collision_events: EventReader<CollisionEvent>,
-    score_events: EventReader<ScoreEvent>,
-    collision_sound: Res<CollisionSound>,
-    score_sound: Res<ScoreSound>,
-) {
-    if !collision_events.is_empty() {
-        collision_events.clear();
-        commands.spawn((AudioPlayer(collision_sound.clone()), PlaybackSettings::DESPAWN));
+impl Default for PaddleTravel {
+    fn default() -> Self {
+        PaddleTravel::symmetric(1.0)
+    }
+}
+
+impl PaddleTravel {
+    fn symmetric(fraction: f32) -> Self {
+        PaddleTravel { top_fraction: fraction, bottom_fraction: fraction }
     }
-    if !score_events.is_empty() {
-        commands.spawn((AudioPlayer(score_sound.clone()), PlaybackSettings::DESPAWN));
+
+    // (下界, 上界)
+    fn bounds(&self) -> (f32, f32) {
+        let (bottom_bound, top_bound) = paddle_bounds();
+        (bottom_bound * self.bottom_fraction, top_bound * self.top_fraction)
     }
 }
 
-fn ball_reset(
-    ball_query: Single<(&mut Velocity, &mut Transform), With<Ball>>,
-    mut score_events: EventReader<ScoreEvent>,
-) {
-    if !score_events.is_empty() {
-        score_events.clear();
-        let (mut ball_velocity, mut ball_transform) = ball_query.into_inner();
-        
-        let sign  = if rand::rng().random_bool(0.5) { 1.0 } else { -1.0 };
-        let temp_num = sign * rand::rng().random_range(0.1..=0.5);
-        ball_velocity.y = ball_velocity.x * temp_num; // 随机发球角度
-
-        **ball_velocity = ball_velocity.normalize() * BALL_SPEED; // 恢复球速
-        
-        if ball_transform.translation.x > 0.0 {
-            ball_transform.translation.x = LEFT_WALL + 40.0;
+// 左右挡板可以分别设置，主菜单的预设同时作用于两侧
+#[derive(Resource, Default)]
+struct PaddleTravelSettings {
+    left: PaddleTravel,
+    right: PaddleTravel,
+}
+
+impl PaddleTravelSettings {
+    fn get(&self, side: PaddleType) -> PaddleTravel {
+        match side {
+            PaddleType::Left => self.left,
+            PaddleType::Right => self.right,
+        }
+    }
+
+    fn label(&self) -> String {
+        let percent = |travel: PaddleTravel| {
+            if travel == PaddleTravel::default() {
+                "FULL".to_string()
+            } else if travel.top_fraction == travel.bottom_fraction {
+                format!("{:.0}%", travel.top_fraction * 100.0)
+            } else {
+                "CUSTOM".to_string()
+            }
+        };
+        if self.left == self.right {
+            percent(self.left)
         } else {
-            ball_transform.translation.x = RIGHT_WALL - 40.0;
+            format!("{} / {}", percent(self.left), percent(self.right))
         }
+    }
 
-        ball_transform.translation.y = 0.0;
+    // 不是预设值时回到完整范围
+    fn next_preset(&self) -> PaddleTravel {
+        let index = PADDLE_TRAVEL_PRESETS
+            .iter()
+            .position(|preset| self.left == self.right && PaddleTravel::symmetric(*preset) == self.left)
+            .map_or(0, |index| index + 1);
+        PaddleTravel::symmetric(PADDLE_TRAVEL_PRESETS[index % PADDLE_TRAVEL_PRESETS.len()])
     }
 }
 
-fn display_winner(
-    mut commands: Commands, 
-    winner: Res<Winner>,
-    asset_server: Res<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    let victory_font = asset_server.load("fonts/Bit3.ttf");
+// 开局热身：计时结束前进球不算分，球按原样重新发球
+#[derive(Resource, Default)]
+struct GracePeriod {
+    secs: f32,
+    timer: Timer,
+}
 
-    let message = match winner.0 {
-            Some(PaddleType::Left) => "PLAYER 1 WIN!",
-            Some(PaddleType::Right) => "PLAYER 2 WIN!",
-            _ => "GAME OVER!",
-        };
+impl GracePeriod {
+    fn active(&self) -> bool {
+        self.secs > 0.0 && !self.timer.finished()
+    }
 
-    // 文本背景框
-    commands.spawn((
-        StateScoped(GameState::GameOver),
-        Mesh2d(meshes.add(Rectangle::new(1000.0, 250.0))),
+    fn label(&self) -> String {
+        if self.secs > 0.0 { format!("{} S", self.secs) } else { "OFF".to_string() }
+    }
+
+    fn next_secs(&self) -> f32 {
+        let index = GRACE_PERIOD_PRESETS
+            .iter()
+            .position(|preset| *preset == self.secs)
+            .unwrap_or(0);
+        GRACE_PERIOD_PRESETS[(index + 1) % GRACE_PERIOD_PRESETS.len()]
+    }
+}
+
+#[derive(Component)]
+struct WarmUpLabel;
+
+// 本局剩余的 let 次数
+#[derive(Resource)]
+struct Lets {
+    remaining: usize,
+}
+
+impl Default for Lets {
+    fn default() -> Self {
+        Lets { remaining: LETS_PER_GAME }
+    }
+}
+
+// 本帧是否有人叫 let，和挡板输入一起记录以便比赛回放
+#[derive(Resource, Default)]
+struct LetRequest(bool);
+
+#[derive(Resource, Default, Deref, DerefMut)]
+struct SpeedrunClock(Stopwatch);
+
+// 最佳用时（秒），保存在存档文件中
+#[derive(Resource)]
+struct SpeedrunRecord(Option<f32>);
+
+// 限时挑战的剩余时间和最快通关用时；用时沿用 SpeedrunClock 计算
+#[derive(Resource)]
+struct TimeAttack {
+    remaining: f32,
+    best: Option<f32>,
+}
+
+impl TimeAttack {
+    fn load() -> Self {
+        TimeAttack {
+            remaining: TIME_ATTACK_SECS,
+            best: load_saved("time_attack_best").and_then(|best| best.parse().ok()),
+        }
+    }
+
+    fn text(&self, locale: Locale) -> String {
+        let time_left = format!("{} {:.1}", locale.text(TextKey::TimeLeft), self.remaining.max(0.0));
+        match self.best {
+            Some(best) => format!("{time_left}  {} {best:.2}", locale.text(TextKey::Best)),
+            None => time_left,
+        }
+    }
+}
+
+#[derive(Component)]
+struct SpeedrunHud;
+
+#[derive(Resource)]
+struct DisplaySettings {
+    present_mode: PresentMode,
+    frame_cap: Option<u32>,
+    resolution: UVec2,
+}
+
+impl DisplaySettings {
+    // 从存档读取上次的选择
+    fn load() -> Self {
+        let present_mode = match load_saved("present_mode").as_deref() {
+            Some("no_vsync") => PresentMode::AutoNoVsync,
+            _ => PresentMode::AutoVsync,
+        };
+        let frame_cap = load_saved("frame_cap").and_then(|cap| cap.parse().ok());
+        let resolution = load_saved("resolution")
+            .and_then(|saved| {
+                let (width, height) = saved.split_once('x')?;
+                Some(UVec2::new(width.parse().ok()?, height.parse().ok()?))
+            })
+            .filter(|resolution| RESOLUTION_PRESETS.contains(resolution))
+            .unwrap_or(RESOLUTION_PRESETS[0]);
+        DisplaySettings { present_mode, frame_cap, resolution }
+    }
+
+    fn save(&self) {
+        let value = match self.present_mode {
+            PresentMode::AutoNoVsync => "no_vsync",
+            _ => "vsync",
+        };
+        store_saved("present_mode", value);
+        let frame_cap = self.frame_cap.map(|cap| cap.to_string()).unwrap_or_default();
+        store_saved("frame_cap", &frame_cap);
+        store_saved("resolution", &format!("{}x{}", self.resolution.x, self.resolution.y));
+    }
+
+    fn resolution_label(&self) -> String {
+        format!("{} X {}", self.resolution.x, self.resolution.y)
+    }
+
+    fn next_resolution(&self) -> UVec2 {
+        let index = RESOLUTION_PRESETS
+            .iter()
+            .position(|preset| *preset == self.resolution)
+            .unwrap_or(0);
+        RESOLUTION_PRESETS[(index + 1) % RESOLUTION_PRESETS.len()]
+    }
+
+    fn frame_cap_label(&self) -> String {
+        match self.frame_cap {
+            Some(cap) => format!("{cap} FPS"),
+            None => "OFF".to_string(),
+        }
+    }
+
+    fn next_frame_cap(&self) -> Option<u32> {
+        let index = FRAME_CAP_PRESETS
+            .iter()
+            .position(|preset| *preset == self.frame_cap)
+            .unwrap_or(0);
+        FRAME_CAP_PRESETS[(index + 1) % FRAME_CAP_PRESETS.len()]
+    }
+
+    // 只提供带自动回退的模式：Immediate/Mailbox 在不支持的平台上会直接崩溃，
+    // 而 AutoNoVsync 会依次尝试 Immediate、Mailbox，最后回退到 Fifo
+    fn present_mode_label(&self) -> &'static str {
+        match self.present_mode {
+            PresentMode::AutoNoVsync => "UNCAPPED",
+            _ => "VSYNC",
+        }
+    }
+
+    fn next_present_mode(&self) -> PresentMode {
+        match self.present_mode {
+            PresentMode::AutoNoVsync => PresentMode::AutoVsync,
+            _ => PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+// 连胜奖励：连胜方开局时球速按连胜场数提升
+#[derive(Resource)]
+struct StreakBonus {
+    enabled: bool,
+    speed_per_win: f32, // 每场连胜增加的球速比例
+    max_streak: usize,  // 超过这个场数不再叠加
+}
+
+impl Default for StreakBonus {
+    fn default() -> Self {
+        StreakBonus {
+            enabled: false,
+            speed_per_win: 0.05,
+            max_streak: 3,
+        }
+    }
+}
+
+impl StreakBonus {
+    fn speed_multiplier(&self, series: &Series) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+        1.0 + self.speed_per_win * series.streak.min(self.max_streak) as f32
+    }
+}
+
+// 同一系列赛（连续 REMATCH）中的连胜记录，回到主菜单时清零
+#[derive(Resource, Default, Clone)]
+struct Series {
+    last_winner: Option<PaddleType>,
+    streak: usize,
+}
+
+// 选手名字，显示在比分上方和胜利文本中，留空则不显示
+#[derive(Resource, Default, Clone)]
+struct PlayerNames {
+    left: String,
+    right: String,
+}
+
+impl PlayerNames {
+    fn load() -> Self {
+        PlayerNames {
+            left: load_saved("name_left").unwrap_or_default(),
+            right: load_saved("name_right").unwrap_or_default(),
+        }
+    }
+
+    fn save(&self) {
+        store_saved("name_left", &self.left);
+        store_saved("name_right", &self.right);
+    }
+
+    fn get(&self, side: PaddleType) -> &str {
+        match side {
+            PaddleType::Left => &self.left,
+            PaddleType::Right => &self.right,
+        }
+    }
+
+    fn get_mut(&mut self, side: PaddleType) -> &mut String {
+        match side {
+            PaddleType::Left => &mut self.left,
+            PaddleType::Right => &mut self.right,
+        }
+    }
+}
+
+// 多人轮流上场的单败淘汰赛。前两名选手使用主菜单输入的名字，其余在存档中用
+// bracket_players 设置（逗号分隔），留空时显示 P3、P4……
+// 每场比赛开始时把两名选手的名字写入 PlayerNames，结束后恢复原来的名字
+#[derive(Resource, Default, Clone)]
+struct Bracket {
+    size: usize,                  // 参赛人数，0 表示不使用
+    players: Vec<String>,
+    entrants: Vec<usize>,         // 本轮还没比赛的选手，前两名是下一场的双方
+    winners: Vec<usize>,          // 本轮已晋级的选手
+    results: Vec<(usize, usize)>, // 本轮已结束的比赛 (胜者, 负者)
+    round: usize,
+    champion: Option<usize>,
+    saved_names: Option<PlayerNames>,
+}
+
+impl Bracket {
+    const SIZES: [usize; 7] = [0, 3, 4, 5, 6, 7, 8];
+
+    fn next_size(&self) -> usize {
+        let index = Bracket::SIZES.iter().position(|size| *size == self.size).unwrap_or(0);
+        Bracket::SIZES[(index + 1) % Bracket::SIZES.len()]
+    }
+
+    fn label(&self, locale: Locale) -> String {
+        match self.size {
+            0 => locale.text(TextKey::Off).to_string(),
+            size => locale.text(TextKey::BracketPlayers).replace("{}", &size.to_string()),
+        }
+    }
+
+    fn in_progress(&self) -> bool {
+        self.entrants.len() >= 2 && self.champion.is_none()
+    }
+
+    fn begin(&mut self, player_names: &PlayerNames) {
+        let base = self.saved_names.get_or_insert_with(|| player_names.clone());
+        let saved_players = load_saved("bracket_players").unwrap_or_default();
+        let mut extra = saved_players.split(',').map(str::trim);
+        self.players = (0..self.size)
+            .map(|index| {
+                let name = match index {
+                    0 => base.left.clone(),
+                    1 => base.right.clone(),
+                    _ => extra.next().unwrap_or_default().to_string(),
+                };
+                if name.is_empty() { format!("P{}", index + 1) } else { name }
+            })
+            .collect();
+        self.entrants = (0..self.size).collect();
+        self.winners.clear();
+        self.results.clear();
+        self.round = 1;
+        self.champion = None;
+    }
+
+    // 记录一场比赛的结果；本轮打完后进入下一轮，落单的选手轮空直接晋级
+    fn record(&mut self, winner: PaddleType) {
+        let (left, right) = (self.entrants[0], self.entrants[1]);
+        let (won, lost) = match winner {
+            PaddleType::Left => (left, right),
+            PaddleType::Right => (right, left),
+        };
+        self.entrants.drain(..2);
+        self.results.push((won, lost));
+        self.winners.push(won);
+        if self.entrants.len() < 2 {
+            let bye = std::mem::take(&mut self.entrants);
+            self.entrants = std::mem::take(&mut self.winners);
+            self.entrants.extend(bye);
+            self.results.clear();
+            self.round += 1;
+            if self.entrants.len() == 1 {
+                self.champion = Some(self.entrants[0]);
+            }
+        }
+    }
+
+    // 放弃当前的淘汰赛，恢复主菜单输入的名字
+    fn end(&mut self, player_names: &mut PlayerNames) {
+        if let Some(saved_names) = self.saved_names.take() {
+            *player_names = saved_names;
+        }
+        self.entrants.clear();
+        self.champion = None;
+    }
+}
+
+// 主菜单上正在输入名字的一方，None 表示没有在输入
+#[derive(Resource, Default, PartialEq, Eq)]
+struct NameEntry(Option<PaddleType>);
+
+// 顶部进度条中一方的填充部分，从外侧向中间增长，到赛点时到达中间
+#[derive(Component)]
+struct ScoreProgress(PaddleType);
+
+// 比分上方的名字
+#[derive(Component)]
+struct PlayerNameUi(PaddleType);
+
+// 淘汰模式中双方剩余的生命
+#[derive(Resource, Clone, Copy)]
+struct Health {
+    left: u32,
+    right: u32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            left: STARTING_HEALTH,
+            right: STARTING_HEALTH,
+        }
+    }
+}
+
+impl Health {
+    fn get(&self, side: PaddleType) -> u32 {
+        match side {
+            PaddleType::Left => self.left,
+            PaddleType::Right => self.right,
+        }
+    }
+
+    fn get_mut(&mut self, side: PaddleType) -> &mut u32 {
+        match side {
+            PaddleType::Left => &mut self.left,
+            PaddleType::Right => &mut self.right,
+        }
+    }
+}
+
+// 比分下方一排生命格，第 index 格在剩余生命数以内时点亮
+#[derive(Component)]
+struct HealthPip {
+    side: PaddleType,
+    index: u32,
+}
+
+// 本局每一分由哪一方得到，以及得分前的比赛状态，按顺序记录
+#[derive(Resource, Default)]
+struct PointHistory(Vec<(PaddleType, MatchSnapshot)>);
+
+// 撤销一分时要恢复的状态：比分、生命、限时挑战剩余时间（含得分加时）和比赛计时
+#[derive(Default, Clone, Copy)]
+struct MatchSnapshot {
+    score: (usize, usize),
+    health: Health,
+    time_attack_remaining: f32,
+    clock: Duration,
+}
+
+// 每个固定帧碰撞检测之前的比赛状态，本帧有人得分时随得分方一起记入 PointHistory
+#[derive(Resource, Default)]
+struct PrePointState(MatchSnapshot);
+
+// 结算画面撤销决胜球后，重新进入比赛时要还原的进度
+#[derive(Resource, Default)]
+struct ResumeMatch(Option<ResumedMatch>);
+
+struct ResumedMatch {
+    scorer: PaddleType,
+    before: MatchSnapshot,
+    history: Vec<(PaddleType, MatchSnapshot)>,
+}
+
+// 进入结算前的系列赛和淘汰赛进度，撤销决胜球时恢复
+#[derive(Resource, Default)]
+struct PreGameOver {
+    series: Series,
+    bracket: Bracket,
+}
+
+#[derive(SystemParam)]
+struct MatchProgress<'w> {
+    score: ResMut<'w, Score>,
+    health: ResMut<'w, Health>,
+    time_attack: ResMut<'w, TimeAttack>,
+    speedrun_clock: ResMut<'w, SpeedrunClock>,
+}
+
+impl MatchProgress<'_> {
+    fn snapshot(&self) -> MatchSnapshot {
+        MatchSnapshot {
+            score: (self.score.0, self.score.1),
+            health: *self.health,
+            time_attack_remaining: self.time_attack.remaining,
+            clock: self.speedrun_clock.elapsed(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: &MatchSnapshot) {
+        (self.score.0, self.score.1) = snapshot.score;
+        *self.health = snapshot.health;
+        self.time_attack.remaining = snapshot.time_attack_remaining;
+        self.speedrun_clock.set_elapsed(snapshot.clock);
+        // 结算时计时已暂停
+        self.speedrun_clock.unpause();
+    }
+}
+
+// 底部显示得分顺序的一排圆点
+#[derive(Component)]
+struct PointHistoryUi;
+
+// 顶部中间的连续击球计数
+#[derive(Component)]
+struct RallyUi;
+
+// 场地中央的发球倒计时数字
+#[derive(Component)]
+struct ServeCountdownUi;
+
+// 自定义的胜利文本，比如写上选手名字；留空时使用默认文本
+// 在存档文件中设置 victory_left / victory_right / victory_draw
+#[derive(Resource, Default)]
+struct VictoryMessages {
+    left: String,
+    right: String,
+    draw: String,
+}
+
+impl VictoryMessages {
+    fn load() -> Self {
+        VictoryMessages {
+            left: load_saved("victory_left").unwrap_or_default(),
+            right: load_saved("victory_right").unwrap_or_default(),
+            draw: load_saved("victory_draw").unwrap_or_default(),
+        }
+    }
+
+    // 优先使用自定义文本，其次用选手名字，最后是默认文本
+    fn message(&self, winner: Option<PaddleType>, names: &PlayerNames, locale: Locale) -> String {
+        let (custom, key) = match winner {
+            Some(PaddleType::Left) => (&self.left, TextKey::Player1Win),
+            Some(PaddleType::Right) => (&self.right, TextKey::Player2Win),
+            None => (&self.draw, TextKey::GameOver),
+        };
+        if !custom.is_empty() {
+            return custom.clone();
+        }
+        match winner.map(|side| names.get(side)) {
+            Some(name) if !name.is_empty() => locale.text(TextKey::NamedWin).replace("{}", name),
+            _ => locale.text(key).to_string(),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+struct Accessibility {
+    reduce_motion: bool, // 关闭闪烁、渐隐等动态效果
+}
+
+// 球撞击上下墙时留下的渐隐印记
+#[derive(Component)]
+struct WallMark {
+    timer: Timer,
+}
+
+#[derive(Resource, Default)]
+struct Theme {
+    center_line: CenterLineColor,
+    background: Background,
+    walls: WallVisibility,
+    vignette: Vignette,
+}
+
+// 各面墙是否显示，默认只显示上下墙
+#[derive(Clone, Copy)]
+struct WallVisibility {
+    top: bool,
+    bottom: bool,
+    left: bool,
+    right: bool,
+}
+
+impl Default for WallVisibility {
+    fn default() -> Self {
+        WallVisibility {
+            top: true,
+            bottom: true,
+            left: false,
+            right: false,
+        }
+    }
+}
+
+impl WallVisibility {
+    fn get_mut(&mut self, wall_type: WallType) -> &mut bool {
+        match wall_type {
+            WallType::Top => &mut self.top,
+            WallType::Bottom => &mut self.bottom,
+            WallType::Left => &mut self.left,
+            WallType::Right => &mut self.right,
+        }
+    }
+
+    fn is_visible(&self, wall_type: WallType) -> bool {
+        match wall_type {
+            WallType::Top => self.top,
+            WallType::Bottom => self.bottom,
+            WallType::Left => self.left,
+            WallType::Right => self.right,
+        }
+    }
+
+    // 列出显示的墙
+    fn label(&self) -> String {
+        let names = [
+            (self.top, "TOP"),
+            (self.bottom, "BOTTOM"),
+            (self.left, "LEFT"),
+            (self.right, "RIGHT"),
+        ];
+        let visible: Vec<&str> = names.iter().filter(|(shown, _)| *shown).map(|(_, name)| *name).collect();
+        if visible.is_empty() { "NONE".to_string() } else { visible.join("/") }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Background {
+    #[default]
+    Plain,
+    Grid,
+}
+
+impl Background {
+    fn label(&self) -> &'static str {
+        match self {
+            Background::Plain => "PLAIN",
+            Background::Grid => "GRID",
+        }
+    }
+
+    fn next(&self) -> Background {
+        match self {
+            Background::Plain => Background::Grid,
+            Background::Grid => Background::Plain,
+        }
+    }
+}
+
+// 场地四周的暗角强度，默认关闭
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Vignette {
+    #[default]
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl Vignette {
+    // 场地边缘的最大不透明度
+    fn intensity(&self) -> f32 {
+        match self {
+            Vignette::Off => 0.0,
+            Vignette::Low => 0.3,
+            Vignette::Medium => 0.5,
+            Vignette::High => 0.7,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Vignette::Off => "OFF",
+            Vignette::Low => "LOW",
+            Vignette::Medium => "MEDIUM",
+            Vignette::High => "HIGH",
+        }
+    }
+
+    fn next(&self) -> Vignette {
+        match self {
+            Vignette::Off => Vignette::Low,
+            Vignette::Low => Vignette::Medium,
+            Vignette::Medium => Vignette::High,
+            Vignette::High => Vignette::Off,
+        }
+    }
+}
+
+// 覆盖整个场地的暗角，位于背景网格之上、所有游戏元素之下，因此不会遮住边缘的球
+#[derive(Component)]
+struct VignetteOverlay;
+
+// 白色径向渐变贴图，透明度从中心向外增加；颜色和强度由 Sprite 的颜色决定
+fn vignette_image() -> Image {
+    let size = VIGNETTE_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let uv = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - Vec2::ONE;
+            let t = ((uv.length() - VIGNETTE_INNER_RADIUS) / (1.0 - VIGNETTE_INNER_RADIUS)).clamp(0.0, 1.0);
+            let alpha = t * t * (3.0 - 2.0 * t);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn vignette_visibility(vignette: Vignette) -> Visibility {
+    match vignette {
+        Vignette::Off => Visibility::Hidden,
+        _ => Visibility::Inherited,
+    }
+}
+
+// 背景网格线，只是装饰，没有 Collider
+#[derive(Component)]
+struct BackgroundGrid;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum CenterLineColor {
+    #[default]
+    White,
+    Gray, // 较暗，不那么显眼
+}
+
+impl CenterLineColor {
+    fn color(&self) -> Color {
+        match self {
+            CenterLineColor::White => Color::WHITE,
+            CenterLineColor::Gray => Color::srgb(0.35, 0.35, 0.35),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            CenterLineColor::White => "WHITE",
+            CenterLineColor::Gray => "GRAY",
+        }
+    }
+
+    fn next(&self) -> CenterLineColor {
+        match self {
+            CenterLineColor::White => CenterLineColor::Gray,
+            CenterLineColor::Gray => CenterLineColor::White,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PaddleKeys {
+    up: KeyCode,
+    down: KeyCode,
+    boost: KeyCode,
+    block: KeyCode,
+}
+
+#[derive(Resource)]
+struct KeyBindings {
+    layout: ControlLayout,
+    left: PaddleKeys,
+    right: PaddleKeys,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::from_layout(ControlLayout::default())
+    }
+}
+
+impl KeyBindings {
+    fn from_layout(layout: ControlLayout) -> Self {
+        let left = PaddleKeys {
+            up: KeyCode::KeyW,
+            down: KeyCode::KeyS,
+            boost: KeyCode::ShiftLeft,
+            block: KeyCode::KeyD,
+        };
+        let right = match layout {
+            ControlLayout::Standard => PaddleKeys {
+                up: KeyCode::ArrowUp,
+                down: KeyCode::ArrowDown,
+                boost: KeyCode::NumpadEnter,
+                block: KeyCode::ArrowLeft,
+            },
+            ControlLayout::SharedKeyboard => PaddleKeys {
+                up: KeyCode::KeyO,
+                down: KeyCode::KeyL,
+                boost: KeyCode::ShiftRight,
+                block: KeyCode::KeyK,
+            },
+        };
+        KeyBindings { layout, left, right }
+    }
+}
+
+// 锦标赛模式：禁用加速键，双方挡板速度完全相同
+#[derive(Resource, Default)]
+struct TournamentMode(bool);
+
+// 两名玩家换座位时交换两组按键控制的挡板
+#[derive(Resource, Default)]
+struct SwapSides(bool);
+
+// 一个挡板的输入来源
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum InputSource {
+    #[default]
+    Keyboard,
+    Gamepad(Entity),
+    Mouse, // 挡板跟随光标高度，左键格挡
+}
+
+impl InputSource {
+    // 手柄按连接顺序编号，从 1 开始
+    fn label(&self, locale: Locale, gamepads: &[Entity]) -> String {
+        match self {
+            InputSource::Keyboard => locale.text(TextKey::Keyboard).to_string(),
+            InputSource::Gamepad(entity) => {
+                let number = gamepads.iter().position(|gamepad| gamepad == entity).map_or(0, |index| index + 1);
+                format!("{} {number}", locale.text(TextKey::Gamepad))
+            }
+            InputSource::Mouse => locale.text(TextKey::Mouse).to_string(),
+        }
+    }
+
+    // 键盘 -> 手柄1 -> 手柄2 ... -> 鼠标 -> 键盘
+    fn next(&self, gamepads: &[Entity]) -> InputSource {
+        let next_index = match self {
+            InputSource::Keyboard => 0,
+            InputSource::Gamepad(entity) => {
+                gamepads.iter().position(|gamepad| gamepad == entity).map_or(0, |index| index + 1)
+            }
+            InputSource::Mouse => return InputSource::Keyboard,
+        };
+        gamepads.get(next_index).map_or(InputSource::Mouse, |gamepad| InputSource::Gamepad(*gamepad))
+    }
+}
+
+// 每个挡板由键盘还是哪个手柄控制；换座位（SwapSides）只影响键盘
+#[derive(Resource, Default)]
+struct ControllerAssignment {
+    left: InputSource,
+    right: InputSource,
+}
+
+impl ControllerAssignment {
+    fn get(&self, side: PaddleType) -> InputSource {
+        match side {
+            PaddleType::Left => self.left,
+            PaddleType::Right => self.right,
+        }
+    }
+
+    fn get_mut(&mut self, side: PaddleType) -> &mut InputSource {
+        match side {
+            PaddleType::Left => &mut self.left,
+            PaddleType::Right => &mut self.right,
+        }
+    }
+}
+
+// 按键布局预设
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum ControlLayout {
+    #[default]
+    Standard,       // W/S + 方向键
+    SharedKeyboard, // W/S + O/L，两人共用键盘主区时不会挤在一起
+}
+
+impl ControlLayout {
+    // 按 左挡板 + 右挡板 的顺序列出按键组
+    fn label(&self, swap_sides: bool) -> String {
+        let right_keys = match self {
+            ControlLayout::Standard => "ARROWS",
+            ControlLayout::SharedKeyboard => "O/L",
+        };
+        if swap_sides {
+            format!("{right_keys} + W/S")
+        } else {
+            format!("W/S + {right_keys}")
+        }
+    }
+
+    fn next(&self) -> ControlLayout {
+        match self {
+            ControlLayout::Standard => ControlLayout::SharedKeyboard,
+            ControlLayout::SharedKeyboard => ControlLayout::Standard,
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum Locale {
+    #[default]
+    English,
+    Chinese,
+}
+
+// 界面文本的键，实际文字由 Locale 查表得到
+#[derive(Clone, Copy)]
+enum TextKey {
+    Player1Win,
+    Player2Win,
+    GameOver,
+    Rematch,
+    RematchSwapped,
+    MainMenu,
+    Title,
+    PressSpaceToStart,
+    Replay,
+    RallyBounces, // 含占位符 {}
+    Rally,
+    BestRally,
+    Time,
+    Best,
+    Mode,
+    CenterLine,
+    Background,
+    Controls,
+    ReduceMotion,
+    PresentMode,
+    FrameCap,
+    Resolution,
+    Language,
+    StreakBonus,
+    SwapSides,
+    Tournament,
+    Names,
+    Bracket,
+    BracketPlayers, // 含占位符 {}
+    BracketRound,   // 含占位符 {}
+    Beat,
+    Versus,
+    Bye,
+    Next,
+    Champion, // 含占位符 {}
+    NamedWin, // 含占位符 {}
+    WarmUp,
+    Spinner,
+    PaddleTravel,
+    Walls,
+    Vignette,
+    Arena,
+    Roulette,
+    Difficulty,
+    Endless,
+    SinglePlayer,
+    TargetScore,
+    WinByTwo,
+    ServeClearNet,
+    LeftInput,
+    RightInput,
+    Keyboard,
+    Gamepad,
+    Mouse,
+    GamepadDisconnected,
+    Let, // 含占位符 {}
+    On,
+    Off,
+    WatchLastMatch,
+    TutorialTitle,
+    TutorialControls,
+    TutorialScoring, // 含占位符 {}
+    TutorialSpeedUp,
+    TutorialDismiss,
+    StandardDescription,
+    SpeedrunDescription,
+    ClassicDescription,
+    ChaosDescription,
+    CaptureDescription,
+    TimeAttackDescription,
+    EliminationDescription,
+    TimeLeft,
+    TimeAttackClear,
+    TimeUp,
+    Paused,
+    PressEscToResume,
+    StandardMode,
+    SpeedrunMode,
+    ClassicMode,
+    ChaosMode,
+    CaptureMode,
+    TimeAttackMode,
+    EliminationMode,
+    EndlessMode,
+    Easy,
+    Normal,
+    Hard,
+    Slow,
+    Fast,
+    SplitBall,
+    GhostObstacle,
+    Portals,
+    PowerUps,
+    SuperCurve,
+    Obstacles,
+    ServeOverNet,
+    Catch,
+    Ai,
+    Split,
+    Ghost,
+    Latency,
+    Milliseconds,
+    Speed,
+    State,
+    ActiveModifiers,
+    NoModifiers,
+}
+
+impl Locale {
+    // Bit3 只有 ASCII 字形，中文界面换用 CJK 字体
+    fn font(&self) -> &'static str {
+        match self {
+            Locale::English => "fonts/Bit3.ttf",
+            Locale::Chinese => "fonts/NotoSansSC-Regular.ttf",
+        }
+    }
+
+    fn text(&self, key: TextKey) -> &'static str {
+        match self {
+            Locale::English => match key {
+                TextKey::Player1Win => "PLAYER 1 WIN!",
+                TextKey::Player2Win => "PLAYER 2 WIN!",
+                TextKey::GameOver => "GAME OVER!",
+                TextKey::Rematch => "REMATCH",
+                TextKey::RematchSwapped => "SWAP SIDES",
+                TextKey::MainMenu => "MAIN MENU",
+                TextKey::Title => "PONG WITH OBSTACLES",
+                TextKey::PressSpaceToStart => "PRESS SPACE TO START",
+                TextKey::Replay => "REPLAY",
+                TextKey::RallyBounces => "THAT RALLY HAD {} BOUNCES!",
+                TextKey::Rally => "RALLY",
+                TextKey::BestRally => "BEST RALLY",
+                TextKey::Time => "TIME",
+                TextKey::Best => "BEST",
+                TextKey::Mode => "MODE",
+                TextKey::CenterLine => "CENTER LINE",
+                TextKey::Background => "BACKGROUND",
+                TextKey::Controls => "CONTROLS",
+                TextKey::ReduceMotion => "REDUCE MOTION",
+                TextKey::PresentMode => "PRESENT MODE",
+                TextKey::FrameCap => "FRAME CAP",
+                TextKey::Resolution => "RESOLUTION",
+                TextKey::Language => "LANGUAGE",
+                TextKey::StreakBonus => "STREAK BONUS",
+                TextKey::SwapSides => "SWAP SIDES",
+                TextKey::Tournament => "TOURNAMENT MODE (NO BOOST)",
+                TextKey::Names => "NAMES (TAB SWITCHES, ENTER DONE)",
+                TextKey::Bracket => "BRACKET",
+                TextKey::BracketPlayers => "{} PLAYERS",
+                TextKey::BracketRound => "BRACKET ROUND {}",
+                TextKey::Beat => "BEAT",
+                TextKey::Versus => "VS",
+                TextKey::Bye => "BYE",
+                TextKey::Next => "NEXT",
+                TextKey::Champion => "CHAMPION: {}",
+                TextKey::NamedWin => "{} WINS!",
+                TextKey::WarmUp => "WARM-UP",
+                TextKey::Spinner => "CENTER SPINNER",
+                TextKey::PaddleTravel => "PADDLE TRAVEL",
+                TextKey::Walls => "VISIBLE WALLS",
+                TextKey::Vignette => "VIGNETTE",
+                TextKey::Arena => "ARENA",
+                TextKey::Roulette => "MODIFIER ROULETTE",
+                TextKey::Difficulty => "DIFFICULTY",
+                TextKey::Endless => "ENDLESS (END QUITS)",
+                TextKey::SinglePlayer => "SINGLE PLAYER",
+                TextKey::TargetScore => "TARGET SCORE",
+                TextKey::WinByTwo => "WIN BY TWO",
+                TextKey::ServeClearNet => "SERVE MUST CROSS NET",
+                TextKey::LeftInput => "LEFT PADDLE",
+                TextKey::RightInput => "RIGHT PADDLE",
+                TextKey::Keyboard => "KEYBOARD",
+                TextKey::Gamepad => "GAMEPAD",
+                TextKey::Mouse => "MOUSE",
+                TextKey::GamepadDisconnected => "GAMEPAD DISCONNECTED, SWITCHED TO KEYBOARD",
+                TextKey::Let => "LET! {} LEFT",
+                TextKey::On => "ON",
+                TextKey::Off => "OFF",
+                TextKey::WatchLastMatch => "P - WATCH LAST MATCH",
+                TextKey::TutorialTitle => "HOW TO PLAY",
+                TextKey::TutorialControls => "W/S AND THE ARROW KEYS MOVE THE PADDLES, HOLD SHIFT TO BOOST, ESC TO PAUSE",
+                TextKey::TutorialScoring => "GET THE BALL PAST YOUR OPPONENT TO SCORE, FIRST TO {} WINS",
+                TextKey::TutorialSpeedUp => "THE BALL SPEEDS UP EVERY TIME A PADDLE RETURNS IT",
+                TextKey::TutorialDismiss => "PRESS SPACE TO CONTINUE",
+                TextKey::StandardDescription => "OBSTACLES ON THE FIELD, THE BALL SPEEDS UP ON EVERY HIT",
+                TextKey::SpeedrunDescription => "REACH THE TARGET SCORE AGAINST THE CLOCK",
+                TextKey::ClassicDescription => "NO OBSTACLES AND A STEADY BALL, JUST LIKE THE ORIGINAL",
+                TextKey::ChaosDescription => "THE BALL MAY SPLIT WHEN IT HITS THE TOP OR BOTTOM WALL",
+                TextKey::CaptureDescription => "HOLD BLOCK TO CATCH THE BALL, RELEASE TO THROW IT",
+                TextKey::TimeAttackDescription => "BEAT THE AI TO THE TARGET SCORE BEFORE TIME RUNS OUT",
+                TextKey::EliminationDescription => "EVERY GOAL CONCEDED COSTS A LIFE, LOSE THEM ALL AND YOU ARE OUT",
+                TextKey::TimeLeft => "TIME LEFT",
+                TextKey::TimeAttackClear => "TIME ATTACK CLEAR!",
+                TextKey::TimeUp => "TIME'S UP!",
+                TextKey::Paused => "PAUSED",
+                TextKey::PressEscToResume => "PRESS ESC TO RESUME",
+                TextKey::StandardMode => "STANDARD",
+                TextKey::SpeedrunMode => "SPEEDRUN",
+                TextKey::ClassicMode => "CLASSIC",
+                TextKey::ChaosMode => "CHAOS",
+                TextKey::CaptureMode => "CAPTURE",
+                TextKey::TimeAttackMode => "TIME ATTACK",
+                TextKey::EliminationMode => "ELIMINATION",
+                TextKey::EndlessMode => "ENDLESS",
+                TextKey::Easy => "EASY",
+                TextKey::Normal => "NORMAL",
+                TextKey::Hard => "HARD",
+                TextKey::Slow => "SLOW",
+                TextKey::Fast => "FAST",
+                TextKey::SplitBall => "SPLIT BALL",
+                TextKey::GhostObstacle => "GHOST OBSTACLE",
+                TextKey::Portals => "PORTALS",
+                TextKey::PowerUps => "POWER-UPS",
+                TextKey::SuperCurve => "SUPER CURVE",
+                TextKey::Obstacles => "OBSTACLES",
+                TextKey::ServeOverNet => "SERVE OVER NET",
+                TextKey::Catch => "CATCH",
+                TextKey::Ai => "AI",
+                TextKey::Split => "SPLIT",
+                TextKey::Ghost => "GHOST",
+                TextKey::Latency => "LATENCY",
+                TextKey::Milliseconds => "MS",
+                TextKey::Speed => "SPEED",
+                TextKey::State => "STATE",
+                TextKey::ActiveModifiers => "MODIFIERS",
+                TextKey::NoModifiers => "NONE",
+            },
+            Locale::Chinese => match key {
+                TextKey::Player1Win => "玩家1获胜！",
+                TextKey::Player2Win => "玩家2获胜！",
+                TextKey::GameOver => "游戏结束！",
+                TextKey::Rematch => "再来一局",
+                TextKey::RematchSwapped => "交换左右再来一局",
+                TextKey::MainMenu => "主菜单",
+                TextKey::Title => "障碍乒乓",
+                TextKey::PressSpaceToStart => "按空格键开始",
+                TextKey::Replay => "回放",
+                TextKey::RallyBounces => "本回合反弹了{}次！",
+                TextKey::Rally => "连击",
+                TextKey::BestRally => "最高连击",
+                TextKey::Time => "用时",
+                TextKey::Best => "最佳",
+                TextKey::Mode => "模式",
+                TextKey::CenterLine => "中线",
+                TextKey::Background => "背景",
+                TextKey::Controls => "按键",
+                TextKey::ReduceMotion => "减少动态效果",
+                TextKey::PresentMode => "显示模式",
+                TextKey::FrameCap => "帧率上限",
+                TextKey::Resolution => "分辨率",
+                TextKey::Language => "语言",
+                TextKey::StreakBonus => "连胜奖励",
+                TextKey::SwapSides => "交换左右",
+                TextKey::Tournament => "锦标赛模式（禁用加速）",
+                TextKey::Names => "名字（TAB 切换，回车完成）",
+                TextKey::Bracket => "淘汰赛",
+                TextKey::BracketPlayers => "{}人",
+                TextKey::BracketRound => "淘汰赛 第{}轮",
+                TextKey::Beat => "胜",
+                TextKey::Versus => "对",
+                TextKey::Bye => "轮空",
+                TextKey::Next => "下一场",
+                TextKey::Champion => "冠军：{}",
+                TextKey::NamedWin => "{}获胜！",
+                TextKey::WarmUp => "热身",
+                TextKey::Spinner => "中央旋转障碍",
+                TextKey::PaddleTravel => "挡板移动范围",
+                TextKey::Walls => "显示的墙",
+                TextKey::Vignette => "暗角",
+                TextKey::Arena => "场地",
+                TextKey::Roulette => "随机修正",
+                TextKey::Difficulty => "难度",
+                TextKey::Endless => "无尽模式（END 退出）",
+                TextKey::SinglePlayer => "单人模式",
+                TextKey::TargetScore => "目标分数",
+                TextKey::WinByTwo => "须领先两分",
+                TextKey::ServeClearNet => "发球须过中线",
+                TextKey::LeftInput => "左挡板",
+                TextKey::RightInput => "右挡板",
+                TextKey::Keyboard => "键盘",
+                TextKey::Gamepad => "手柄",
+                TextKey::Mouse => "鼠标",
+                TextKey::GamepadDisconnected => "手柄已断开，改用键盘",
+                TextKey::Let => "重新发球！还剩{}次",
+                TextKey::On => "开",
+                TextKey::Off => "关",
+                TextKey::WatchLastMatch => "P - 观看上一局",
+                TextKey::TutorialTitle => "玩法说明",
+                TextKey::TutorialControls => "W/S 和方向键移动挡板，按住 SHIFT 加速，Esc 暂停",
+                TextKey::TutorialScoring => "让球越过对手的挡板即可得分，先得{}分者获胜",
+                TextKey::TutorialSpeedUp => "挡板每接一次球，球速都会提升",
+                TextKey::TutorialDismiss => "按空格键继续",
+                TextKey::StandardDescription => "场上有障碍，每次接球后球速提升",
+                TextKey::SpeedrunDescription => "计时挑战，尽快达到目标分数",
+                TextKey::ClassicDescription => "没有障碍，球速不变，原汁原味",
+                TextKey::ChaosDescription => "球撞到上下墙时可能分裂出更多的球",
+                TextKey::CaptureDescription => "按住格挡键接住球，松开后掷出",
+                TextKey::TimeAttackDescription => "时间耗尽前对电脑拿到目标分数，每得一分延长时间",
+                TextKey::EliminationDescription => "每失一球扣一条命，生命耗尽即出局",
+                TextKey::TimeLeft => "剩余时间",
+                TextKey::TimeAttackClear => "挑战成功！",
+                TextKey::TimeUp => "时间到！",
+                TextKey::Paused => "已暂停",
+                TextKey::PressEscToResume => "按 Esc 继续",
+                TextKey::StandardMode => "标准",
+                TextKey::SpeedrunMode => "竞速",
+                TextKey::ClassicMode => "经典",
+                TextKey::ChaosMode => "混战",
+                TextKey::CaptureMode => "接球",
+                TextKey::TimeAttackMode => "限时挑战",
+                TextKey::EliminationMode => "淘汰",
+                TextKey::EndlessMode => "无尽",
+                TextKey::Easy => "简单",
+                TextKey::Normal => "普通",
+                TextKey::Hard => "困难",
+                TextKey::Slow => "慢",
+                TextKey::Fast => "快",
+                TextKey::SplitBall => "分裂球",
+                TextKey::GhostObstacle => "幽灵障碍",
+                TextKey::Portals => "传送门",
+                TextKey::PowerUps => "道具",
+                TextKey::SuperCurve => "超强弧线",
+                TextKey::Obstacles => "障碍",
+                TextKey::ServeOverNet => "发球过网",
+                TextKey::Catch => "接住球",
+                TextKey::Ai => "电脑",
+                TextKey::Split => "分裂",
+                TextKey::Ghost => "幽灵",
+                TextKey::Latency => "延迟",
+                TextKey::Milliseconds => "毫秒",
+                TextKey::Speed => "球速",
+                TextKey::State => "状态",
+                TextKey::ActiveModifiers => "修正项",
+                TextKey::NoModifiers => "无",
+            },
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Locale::English => "ENGLISH",
+            Locale::Chinese => "中文",
+        }
+    }
+
+    // 字体文件没有随游戏发布时不提供该语言，否则界面文字会整片空白
+    fn available(&self) -> bool {
+        FileAssetReader::get_base_path().join("assets").join(self.font()).exists()
+    }
+
+    fn next(&self) -> Locale {
+        let next = match self {
+            Locale::English => Locale::Chinese,
+            Locale::Chinese => Locale::English,
+        };
+        if next.available() { next } else { *self }
+    }
+}
+
+// 主菜单中显示当前设置的文本行
+#[derive(Component, Clone, Copy)]
+enum SettingLabel {
+    Mode,
+    ModeDescription,
+    CenterLine,
+    Background,
+    Controls,
+    ReduceMotion,
+    PresentMode,
+    FrameCap,
+    Resolution,
+    Language,
+    StreakBonus,
+    SwapSides,
+    Tournament,
+    PlayerNames,
+    WarmUp,
+    Spinner,
+    PaddleTravel,
+    Walls,
+    Vignette,
+    Arena,
+    Roulette,
+    Difficulty,
+    Endless,
+    SinglePlayer,
+    TargetScore,
+    WinByTwo,
+    ServeClearNet,
+    Bracket,
+    LeftInput,
+    RightInput,
+}
+
+impl SettingLabel {
+    const ALL: [SettingLabel; 30] = [
+        SettingLabel::PlayerNames,
+        SettingLabel::Bracket,
+        SettingLabel::Mode,
+        SettingLabel::ModeDescription,
+        SettingLabel::Difficulty,
+        SettingLabel::TargetScore,
+        SettingLabel::WinByTwo,
+        SettingLabel::ServeClearNet,
+        SettingLabel::Endless,
+        SettingLabel::SinglePlayer,
+        SettingLabel::Spinner,
+        SettingLabel::PaddleTravel,
+        SettingLabel::Roulette,
+        SettingLabel::CenterLine,
+        SettingLabel::Background,
+        SettingLabel::Walls,
+        SettingLabel::Vignette,
+        SettingLabel::Arena,
+        SettingLabel::Controls,
+        SettingLabel::LeftInput,
+        SettingLabel::RightInput,
+        SettingLabel::SwapSides,
+        SettingLabel::ReduceMotion,
+        SettingLabel::PresentMode,
+        SettingLabel::FrameCap,
+        SettingLabel::Resolution,
+        SettingLabel::Language,
+        SettingLabel::StreakBonus,
+        SettingLabel::Tournament,
+        SettingLabel::WarmUp,
+    ];
+
+    fn text(&self, settings: &SettingsView) -> String {
+        let locale = *settings.locale;
+        let (key, name, value) = match self {
+            SettingLabel::Mode => {
+                ("M / LEFT / RIGHT", TextKey::Mode, settings.game_mode.label(locale).to_string())
+            }
+            // 说明行不带按键提示
+            SettingLabel::ModeDescription => {
+                return locale.text(settings.game_mode.description()).to_string();
+            }
+            SettingLabel::CenterLine => {
+                ("L", TextKey::CenterLine, settings.theme.center_line.label().to_string())
+            }
+            SettingLabel::Background => {
+                ("C", TextKey::Background, settings.theme.background.label().to_string())
+            }
+            SettingLabel::Walls => ("1-4", TextKey::Walls, settings.theme.walls.label()),
+            SettingLabel::Vignette => {
+                ("I", TextKey::Vignette, settings.theme.vignette.label().to_string())
+            }
+            SettingLabel::Arena => ("A", TextKey::Arena, settings.match_options.arena.label().to_string()),
+            SettingLabel::Controls => {
+                ("K", TextKey::Controls, settings.key_bindings.layout.label(settings.swap_sides.0))
+            }
+            SettingLabel::LeftInput => ("Q", TextKey::LeftInput, settings.controllers.label(PaddleType::Left, locale)),
+            SettingLabel::RightInput => ("U", TextKey::RightInput, settings.controllers.label(PaddleType::Right, locale)),
+            SettingLabel::ReduceMotion => (
+                "R",
+                TextKey::ReduceMotion,
+                on_off(locale, settings.accessibility.reduce_motion).to_string(),
+            ),
+            SettingLabel::PresentMode => {
+                ("V", TextKey::PresentMode, settings.display.present_mode_label().to_string())
+            }
+            SettingLabel::FrameCap => ("F", TextKey::FrameCap, settings.display.frame_cap_label()),
+            SettingLabel::Resolution => ("Z", TextKey::Resolution, settings.display.resolution_label()),
+            SettingLabel::Language => ("G", TextKey::Language, locale.label().to_string()),
+            SettingLabel::SwapSides => {
+                ("X", TextKey::SwapSides, on_off(locale, settings.swap_sides.0).to_string())
+            }
+            SettingLabel::Tournament => {
+                ("T", TextKey::Tournament, on_off(locale, settings.tournament.0).to_string())
+            }
+            SettingLabel::WarmUp => ("W", TextKey::WarmUp, settings.grace_period.label()),
+            SettingLabel::Spinner => {
+                ("S", TextKey::Spinner, settings.center_spinner.speed.label(locale).to_string())
+            }
+            SettingLabel::PaddleTravel => ("H", TextKey::PaddleTravel, settings.paddle_travel.label()),
+            SettingLabel::Difficulty => {
+                ("D", TextKey::Difficulty, settings.match_options.difficulty.label(locale).to_string())
+            }
+            SettingLabel::Bracket => ("Y", TextKey::Bracket, settings.match_options.bracket.label(locale)),
+            SettingLabel::Endless => {
+                ("E", TextKey::Endless, on_off(locale, settings.match_options.endless.0).to_string())
+            }
+            SettingLabel::TargetScore => (
+                "- / =",
+                TextKey::TargetScore,
+                settings.match_options.match_settings.target_score.to_string(),
+            ),
+            SettingLabel::WinByTwo => (
+                "5",
+                TextKey::WinByTwo,
+                on_off(locale, settings.match_options.match_settings.win_by_two).to_string(),
+            ),
+            SettingLabel::ServeClearNet => (
+                "6",
+                TextKey::ServeClearNet,
+                on_off(locale, settings.match_options.match_settings.serve_must_clear_net).to_string(),
+            ),
+            SettingLabel::SinglePlayer => (
+                "J",
+                TextKey::SinglePlayer,
+                on_off(locale, settings.match_options.single_player.0).to_string(),
+            ),
+            SettingLabel::Roulette => {
+                ("O", TextKey::Roulette, on_off(locale, settings.match_options.roulette.enabled()).to_string())
+            }
+            SettingLabel::StreakBonus => (
+                "B",
+                TextKey::StreakBonus,
+                on_off(locale, settings.streak_bonus.enabled).to_string(),
+            ),
+            SettingLabel::PlayerNames => {
+                // 正在输入的一方末尾显示光标
+                let name = |side: PaddleType| {
+                    let cursor = if settings.name_entry.0 == Some(side) { "_" } else { "" };
+                    format!("{}{cursor}", settings.player_names.get(side))
+                };
+                ("N", TextKey::Names, format!("{} / {}", name(PaddleType::Left), name(PaddleType::Right)))
+            }
+        };
+        format!("{key} - {}: {value}", locale.text(name))
+    }
+}
+
+// 主菜单读取的全部设置
+#[derive(SystemParam)]
+struct SettingsView<'w, 's> {
+    game_mode: Res<'w, GameMode>,
+    theme: Res<'w, Theme>,
+    key_bindings: Res<'w, KeyBindings>,
+    accessibility: Res<'w, Accessibility>,
+    display: Res<'w, DisplaySettings>,
+    locale: Res<'w, Locale>,
+    streak_bonus: Res<'w, StreakBonus>,
+    swap_sides: Res<'w, SwapSides>,
+    tournament: Res<'w, TournamentMode>,
+    player_names: Res<'w, PlayerNames>,
+    name_entry: Res<'w, NameEntry>,
+    grace_period: Res<'w, GracePeriod>,
+    center_spinner: Res<'w, CenterSpinner>,
+    paddle_travel: Res<'w, PaddleTravelSettings>,
+    match_options: MatchOptionsView<'w>,
+    controllers: ControllersView<'w, 's>,
+}
+
+#[derive(SystemParam)]
+struct ControllersView<'w, 's> {
+    assignment: Res<'w, ControllerAssignment>,
+    gamepads: Query<'w, 's, Entity, With<Gamepad>>,
+}
+
+impl ControllersView<'_, '_> {
+    fn label(&self, side: PaddleType, locale: Locale) -> String {
+        self.assignment.get(side).label(locale, &sorted_gamepads(&self.gamepads))
+    }
+}
+
+// 按实体排序，编号在连接期间保持稳定
+fn sorted_gamepads(gamepads: &Query<Entity, With<Gamepad>>) -> Vec<Entity> {
+    let mut gamepads = gamepads.iter().collect::<Vec<_>>();
+    gamepads.sort();
+    gamepads
+}
+
+// SettingsView 的字段数已到上限，match_options_keyboard 管理的选项单独分组
+#[derive(SystemParam)]
+struct MatchOptionsView<'w> {
+    roulette: Res<'w, ModifierRoulette>,
+    difficulty: Res<'w, Difficulty>,
+    endless: Res<'w, EndlessMode>,
+    single_player: Res<'w, SinglePlayer>,
+    match_settings: Res<'w, MatchSettings>,
+    arena: Res<'w, Arena>,
+    bracket: Res<'w, Bracket>,
+}
+
+fn on_off(locale: Locale, enabled: bool) -> &'static str {
+    locale.text(if enabled { TextKey::On } else { TextKey::Off })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MenuAction {
+    Rematch,        // 保留当前设置重新开始
+    RematchSwapped, // 交换左右按键后重新开始
+    MainMenu,       // 返回主菜单，设置恢复默认
+}
+
+// 可用上下方向键选择、回车确认的菜单项
+#[derive(Component)]
+struct MenuItem {
+    index: usize,
+    action: MenuAction,
+}
+
+#[derive(Resource, Default)]
+struct MenuCursor(usize);
+
+#[derive(Component)]
+struct VictoryText;
+
+#[derive(Component)]
+struct TextBackground;
+
+#[derive(Component, Deref, DerefMut)]
+struct Velocity(Vec2);
+
+// 最后一次击球的挡板，得分后清空
+#[derive(Resource, Default)]
+struct LastTouch(Option<PaddleType>);
+
+#[derive(Resource)]
+struct PowerUpSpawner(Timer);
+
+impl Default for PowerUpSpawner {
+    fn default() -> Self {
+        PowerUpSpawner(Timer::from_seconds(POWER_UP_INTERVAL_SECS, TimerMode::Repeating))
+    }
+}
+
+// 场上等待拾取的道具
+#[derive(Component)]
+struct PowerUp;
+
+// 遮暗对手半场的遮罩，计时结束后销毁
+#[derive(Component)]
+struct Blackout {
+    side: PaddleType,
+    timer: Timer,
+}
+
+// 碰撞体的恢复系数，反弹后法向速度乘以该值；没有该组件时视为 1.0
+#[derive(Component)]
+struct Restitution(f32);
+
+// 幽灵障碍：球每次接触时按该概率直接穿过
+#[derive(Component)]
+struct PhaseChance(f32);
+
+// 碰撞体专属的撞击音效；没有该组件时使用默认的碰撞声
+#[derive(Component)]
+struct HitSound(Handle<AudioSource>);
+
+// 球的旋转，使速度方向随时间偏转形成弧线
+#[derive(Component, Default)]
+struct Spin(f32);
+
+#[derive(Event)]
+struct CollisionEvent {
+    position: Vec2,    // 接触点
+    speed: f32,        // 碰撞前的球速
+    side: Collision,   // 球撞在碰撞体的哪一面
+    kind: ColliderKind,
+    collider: Entity,
+    sound: Option<Handle<AudioSource>>, // 碰撞体的 HitSound
+}
+
+// 被撞的碰撞体种类，墙和挡板另外带上具体是哪一个
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ColliderKind {
+    Wall(WallType),
+    Paddle(PaddleType),
+    Obstacle,
+}
+
+#[derive(Event, Default)]
+enum ScoreEvent {
+    #[default]
+    Player1Scored,
+    Player2Scored,
+}
+
+impl ScoreEvent {
+    // 球发向刚失分的一方
+    fn serve_toward(&self) -> PaddleType {
+        match self {
+            ScoreEvent::Player1Scored => PaddleType::Right,
+            ScoreEvent::Player2Scored => PaddleType::Left,
+        }
+    }
+}
+
+// 当前回合的反弹次数（挡板+墙体+障碍）
+#[derive(Resource)]
+struct BounceCount(usize);
+
+// 当前回合连续击球的次数（只算挡板），以及本局的最高纪录
+#[derive(Resource, Default)]
+struct Rally {
+    current: usize,
+    best: usize,
+}
+
+#[derive(Component, Deref, DerefMut)]
+struct BounceMessage(Timer);
+
+#[derive(Resource)]
+struct AudioSettings {
+    pan_strength: f32, // 挡板击球音效偏向击球一侧的程度，0 为不偏移
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings { pan_strength: 0.3 }
+    }
+}
+
+#[derive(Resource, Deref)]
+struct CollisionSound(Handle<AudioSource>);
+
+#[derive(Resource, Deref)]
+struct ScoreSound(Handle<AudioSource>);
+
+// 上一次播放碰撞声的时刻，用来合并短时间内的连续碰撞
+#[derive(Resource, Default)]
+struct LastCollisionSound(Option<Duration>);
+
+// 球进入高温状态时的提示音
+#[derive(Resource, Deref)]
+struct WarningTone(Handle<Pitch>);
+
+// 比赛结束时的和弦：有胜者时为大三和弦，平局为小三和弦。
+// 存档中设置了 victory_sound（assets 下的路径）时改为播放该音效
+#[derive(Resource)]
+struct VictoryJingle {
+    custom: Option<Handle<AudioSource>>,
+    win: Vec<Handle<Pitch>>,
+    draw: Vec<Handle<Pitch>>,
+}
+
+// 球是否处于高温状态，只在跨过阈值时播放提示音
+#[derive(Resource, Default)]
+struct HotBall(bool);
+
+// 高温状态下球周围的光晕
+#[derive(Component)]
+struct HotGlow;
+
+// 加速时挡板上的提示效果，是挡板的子实体
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum BoostEffect {
+    Outline, // 静态描边
+    Streak,  // 拖在移动方向后面的尾迹，减少动态效果时不显示
+}
+
+#[derive(Component, Default)]
+struct Collider;
+
+// 调试用：绘制碰撞体轮廓
+#[derive(Resource)]
+struct DebugColliders(bool);
+
+// 观战用：显示双方当前的输入，F2 开关
+#[derive(Resource, Default)]
+struct InputOverlay(bool);
+
+#[derive(Component)]
+struct InputOverlayUi;
+
+// 在左上角显示当前球速，F3 开关
+#[derive(Resource, Default)]
+struct SpeedHud(bool);
+
+#[derive(Component)]
+struct SpeedHudUi;
+
+// 列出当前状态、模式、难度和生效的修正项，F6 开关；在菜单之上显示
+#[derive(Resource, Default)]
+struct StateOverlay(bool);
+
+#[derive(Component)]
+struct StateOverlayUi;
+
+// 从球心画出指向运动方向的箭头，长度随球速变化，F4 开关，默认关闭
+#[derive(Resource, Default)]
+struct VelocityArrow(bool);
+
+// 调试用：测量按下移动键到挡板开始移动的延迟，F5 开关
+#[derive(Resource, Default)]
+struct LatencyProbe {
+    active: bool,
+    pressed_at: [Option<Instant>; 2], // 左右挡板各自按下移动键的时刻
+    last_ms: [Option<f32>; 2],        // 最近一次测得的延迟
+}
+
+#[derive(Component)]
+struct LatencyHudUi;
+
+// 常驻的界面文字，切换语言时跟着换字体
+#[derive(Component)]
+struct LocalizedFont;
+
+// 输入显示中的一个指示灯
+#[derive(Component, Clone, Copy)]
+struct InputIndicator {
+    side: PaddleType,
+    input: InputKind,
+}
+
+#[derive(Clone, Copy)]
+enum InputKind {
+    Up,
+    Down,
+    Boost,
+    Block,
+}
+
+impl InputKind {
+    fn label(&self) -> &'static str {
+        match self {
+            InputKind::Up => "^",
+            InputKind::Down => "v",
+            InputKind::Boost => "BOOST",
+            InputKind::Block => "BLOCK",
+        }
+    }
+
+    fn is_active(&self, command: &PaddleCommand) -> bool {
+        match self {
+            InputKind::Up => command.direction > 0.0,
+            InputKind::Down => command.direction < 0.0,
+            InputKind::Boost => command.boost,
+            InputKind::Block => command.block,
+        }
+    }
+}
+
+// 场地宽高比；高度固定为上下墙之间的距离，宽度由宽高比推出
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum ArenaAspect {
+    #[default]
+    Classic,
+    Widescreen,
+}
+
+#[derive(Resource, Default)]
+struct Arena {
+    aspect: ArenaAspect,
+}
+
+impl Arena {
+    // 从存档读取上次的选择
+    fn load() -> Self {
+        let aspect = match load_saved("arena_aspect").as_deref() {
+            Some("widescreen") => ArenaAspect::Widescreen,
+            _ => ArenaAspect::Classic,
+        };
+        Arena { aspect }
+    }
+
+    fn save(&self) {
+        let value = match self.aspect {
+            ArenaAspect::Classic => "classic",
+            ArenaAspect::Widescreen => "widescreen",
+        };
+        store_saved("arena_aspect", value);
+    }
+
+    // 宽 / 高
+    fn aspect_ratio(&self) -> f32 {
+        match self.aspect {
+            ArenaAspect::Classic => CLASSIC_ARENA_WIDTH / (TOP_WALL - BOTTOM_WALL),
+            ArenaAspect::Widescreen => 16.0 / 9.0,
+        }
+    }
+
+    fn right(&self) -> f32 {
+        (TOP_WALL - BOTTOM_WALL) * self.aspect_ratio() / 2.0
+    }
+
+    fn left(&self) -> f32 {
+        -self.right()
+    }
+
+    fn size(&self) -> Vec2 {
+        Vec2::new(self.right() - self.left(), TOP_WALL - BOTTOM_WALL)
+    }
+
+    fn label(&self) -> &'static str {
+        match self.aspect {
+            ArenaAspect::Classic => "CLASSIC",
+            ArenaAspect::Widescreen => "16:9",
+        }
+    }
+
+    fn next_aspect(&self) -> ArenaAspect {
+        match self.aspect {
+            ArenaAspect::Classic => ArenaAspect::Widescreen,
+            ArenaAspect::Widescreen => ArenaAspect::Classic,
+        }
+    }
+}
+
+#[derive(Component)]
+#[require(Sprite, Transform, Collider)]
+struct Wall;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallType {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+enum WallLocation {
+    Left,
+    Right,
+    Bottom,
+    Top,
+}
+
+impl WallLocation {
+    // 墙体中心位置
+    fn position(&self, arena: &Arena) -> Vec2 {
+        match self {
+            WallLocation::Left => Vec2::new(arena.left(), 0.),
+            WallLocation::Right => Vec2::new(arena.right(), 0.),
+            WallLocation::Bottom => Vec2::new(0., BOTTOM_WALL),
+            WallLocation::Top => Vec2::new(0., TOP_WALL),
+        }
+    }
+
+    // 墙面尺寸
+    fn size(&self, arena: &Arena) -> Vec2 {
+        let arena_height = TOP_WALL - BOTTOM_WALL;
+        let arena_width = arena.right() - arena.left();
+
+        assert!(arena_height > 0.0);
+        assert!(arena_width > 0.0);
+
+        match self {
+            WallLocation::Left | WallLocation::Right => {
+                Vec2::new(WALL_THICKNESS, arena_height + VERTICAL_WALL_THICKNESS)
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                Vec2::new(arena_width + WALL_THICKNESS, VERTICAL_WALL_THICKNESS)
+            }
+        }
+    }
+}
+
+impl Wall {
+    fn new(location: WallLocation, arena: &Arena) -> (Wall, WallType, Sprite, Transform) {
+        // 上下墙白色，左右墙不可见
+        let color = match location{
+            WallLocation::Left | WallLocation::Right => {
+                Color::NONE
+            }
+            WallLocation::Bottom | WallLocation::Top => {
+                WALL_COLOR
+            }
+        };
+        let walltype = match location{
+            WallLocation::Left => {
+                WallType::Left
+            }
+            WallLocation::Right => {
+                WallType::Right
+            }
+            WallLocation::Top => {
+                WallType::Top
+            }
+            WallLocation::Bottom => {
+                WallType::Bottom
+            }
+        };
+        (
+            Wall,
+            walltype,
+            Sprite::from_color(color, Vec2::ONE),
+            Transform {
+                translation: location.position(arena).extend(0.0),
+                scale: location.size(arena).extend(1.0),
+                ..default()
+            },
+        )
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+    modifiers: Res<Modifiers>,
+    theme: Res<Theme>,
+    game_mode: Res<GameMode>,
+    mut rng: ResMut<GameRng>,
+    mut recording: ResMut<MatchRecording>,
+    mut match_replay: ResMut<MatchReplay>,
+    mut pitch_assets: ResMut<Assets<Pitch>>,
+    mut images: ResMut<Assets<Image>>,
+    arena: Res<Arena>,
+    obstacle_layout: Res<ObstacleLayout>,
+) {
+    // Camera
+    // 至少显示设计高度和整个场地宽度，不同分辨率下场地都完整显示
+    commands.spawn((
+        Camera2d,
+        Projection::from(OrthographicProjection {
+            scaling_mode: ScalingMode::AutoMin {
+                min_width: arena.size().x,
+                min_height: DESIGN_HEIGHT,
+            },
+            ..OrthographicProjection::default_2d()
+        }),
+        SpatialListener::new(2.0),
+    ));
+
+    // Sound
+    let ball_collision_sound = asset_server.load("sounds/pong_collision.ogg");
+    commands.insert_resource(CollisionSound(ball_collision_sound));
+
+    let score_sound = asset_server.load("sounds/score.ogg");
+    commands.insert_resource(ScoreSound(score_sound));
+
+    let warning_tone = pitch_assets.add(Pitch::new(880.0, Duration::from_millis(120)));
+    commands.insert_resource(WarningTone(warning_tone));
+
+    let mut chord = |frequencies: [f32; 3]| {
+        frequencies
+            .map(|frequency| pitch_assets.add(Pitch::new(frequency, Duration::from_millis(600))))
+            .to_vec()
+    };
+    commands.insert_resource(VictoryJingle {
+        custom: load_saved("victory_sound")
+            .filter(|path| !path.is_empty())
+            .map(|path| asset_server.load(path)),
+        win: chord([523.25, 659.25, 783.99]),
+        draw: chord([440.0, 523.25, 659.25]),
+    });
+
+    // Paddle 1
+    commands.spawn((
+        Sprite::from_color(Color::WHITE, Vec2::ONE),
+        Transform {
+            translation: Vec3::new(PaddleType::Left.column_x(&arena), 0.0, 0.0),
+            scale: PADDLE_SIZE.extend(1.0),
+            ..default()
+        },
+        Paddle,
+        PaddleType::Left,
+        PaddleVelocity(0.0),
+        Blocking::default(),
+        Collider,
+        children![boost_effect(BoostEffect::Outline), boost_effect(BoostEffect::Streak)],
+    ));
+
+    // Paddle 2
+    commands.spawn((
+        Sprite::from_color(Color::WHITE, Vec2::ONE),
+        Transform {
+            translation: Vec3::new(PaddleType::Right.column_x(&arena), 0.0, 0.0),
+            scale: PADDLE_SIZE.extend(1.0),
+            ..default()
+        },
+        Paddle,
+        PaddleType::Right,
+        PaddleVelocity(0.0),
+        Blocking::default(),
+        Collider,
+        children![boost_effect(BoostEffect::Outline), boost_effect(BoostEffect::Streak)],
+    ));
+
+    // Walls
+    commands.spawn(Wall::new(WallLocation::Left, &arena));
+    commands.spawn(Wall::new(WallLocation::Right, &arena));
+    commands.spawn(Wall::new(WallLocation::Bottom, &arena));
+    commands.spawn(Wall::new(WallLocation::Top, &arena));
+
+    // Ball
+    commands.spawn((
+        Sprite::from_color(Color::WHITE, Vec2::ONE),
+        Transform {
+            translation: serve_position(PaddleType::Right, &arena).extend(1.0),
+            scale: Vec3::new(BALL_SIZE, BALL_SIZE, 1.0),
+            ..default()
+        },
+        Ball,
+        Velocity(INITIAL_BALL_DIRECTION.normalize() * BALL_SPEED),
+        Spin::default(),
+        children![(
+            HotGlow,
+            Sprite::from_color(HOT_BALL_COLOR.with_alpha(0.35), Vec2::ONE),
+            Transform {
+                translation: Vec3::new(0.0, 0.0, -0.1),
+                scale: Vec3::new(1.8, 1.8, 1.0),
+                ..default()
+            },
+            Visibility::Hidden,
+        )],
+    ));
+
+    // Background grid，放在所有游戏元素之后
+    let grid_visibility = match theme.background {
+        Background::Plain => Visibility::Hidden,
+        Background::Grid => Visibility::Inherited,
+    };
+    let arena_size = arena.size();
+    let mut x = arena.left() + BACKGROUND_GRID_SPACING;
+    while x < arena.right() {
+        commands.spawn((
+            BackgroundGrid,
+            Sprite::from_color(BACKGROUND_GRID_COLOR, Vec2::ONE),
+            Transform {
+                translation: Vec3::new(x, 0.0, -1.0),
+                scale: Vec3::new(2.0, arena_size.y, 1.0),
+                ..default()
+            },
+            grid_visibility,
+        ));
+        x += BACKGROUND_GRID_SPACING;
+    }
+    let mut y = BOTTOM_WALL + BACKGROUND_GRID_SPACING;
+    while y < TOP_WALL {
+        commands.spawn((
+            BackgroundGrid,
+            Sprite::from_color(BACKGROUND_GRID_COLOR, Vec2::ONE),
+            Transform {
+                translation: Vec3::new(0.0, y, -1.0),
+                scale: Vec3::new(arena_size.x, 2.0, 1.0),
+                ..default()
+            },
+            grid_visibility,
+        ));
+        y += BACKGROUND_GRID_SPACING;
+    }
+
+    // Vignette
+    commands.spawn((
+        VignetteOverlay,
+        Sprite {
+            image: images.add(vignette_image()),
+            color: Color::BLACK.with_alpha(theme.vignette.intensity()),
+            custom_size: Some(arena_size),
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, -0.5),
+        vignette_visibility(theme.vignette),
+    ));
+
+    // DashedLineSegment
+    let center_line_start = Vec3::new(0.0, TOP_WALL, 0.0);
+    let center_line_end = Vec3::new(0.0, BOTTOM_WALL, 0.0);
+    let total_length = center_line_start.distance(center_line_end);
+    let mut offset = 10.0;
+    while offset < total_length{
+        let position = center_line_start + Vec3::new(0.0, -offset, 0.0);
+        commands.spawn((
+            Mesh2d(meshes.add(Rectangle::new(DASHEDLINE_SIZE, DASHEDLINE_SIZE))),
+            MeshMaterial2d(materials.add(theme.center_line.color())),
+            Transform::from_translation(position)
+                .with_scale(Vec3::ONE),
+            DashedLineSegment,
+        ));
+        offset += GAP_BETWEEN_DASHEDLINESEGMENTS;
+    }
+
+    // Scoreboard
+    let scoreboard_font = asset_server.load("fonts/Bit3.ttf");
+    commands.spawn((
+        Text::new(""),
+        ScoreboardUi(PaddleType::Left),
+        scoreboard_node(PaddleType::Left),
+        children![(
+            TextSpan::default(),
+            TextFont {
+                font: scoreboard_font.clone(),
+                font_size: SCOREBOARD_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        )],
+    ));
+    commands.spawn((
+        Text::new(""),
+        ScoreboardUi(PaddleType::Right),
+        scoreboard_node(PaddleType::Right),
+        children![(
+            TextSpan::default(),
+            TextFont {
+                font: scoreboard_font.clone(),
+                font_size: SCOREBOARD_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        )],
+    ));
+
+    // 顶部的得分进度条，中间分开
+    let progress_half = |side: PaddleType, justify_content: JustifyContent, color: Color| {
+        (
+            Node {
+                width: Val::Percent(50.0),
+                height: Val::Percent(100.0),
+                justify_content,
+                ..default()
+            },
+            children![(
+                ScoreProgress(side),
+                Node {
+                    width: Val::Percent(0.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                BackgroundColor(color),
+            )],
+        )
+    };
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(4.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Node {
+                width: Val::Px(600.0),
+                height: Val::Px(6.0),
+                column_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+            children![
+                progress_half(PaddleType::Left, JustifyContent::FlexStart, PLAYER_COLORS[0]),
+                progress_half(PaddleType::Right, JustifyContent::FlexEnd, PLAYER_COLORS[1]),
+            ],
+        )],
+    ));
+
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            ServeCountdownUi,
+            Text::new(""),
+            TextFont {
+                font: scoreboard_font.clone(),
+                font_size: TITLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        )],
+    ));
+
+    commands.spawn((
+        RallyUi,
+        LocalizedFont,
+        Text::new(""),
+        TextFont {
+            font: scoreboard_font.clone(),
+            font_size: RALLY_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(14.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        PointHistoryUi,
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            column_gap: Val::Px(6.0),
+            ..default()
+        },
+    ));
+
+    // 名字显示在各自的比分上方
+    for (side, left, right, margin) in [
+        (PaddleType::Left, Val::Auto, Val::Percent(50.0), UiRect::right(Val::Px(SCOREBOARD_CENTER_GAP))),
+        (PaddleType::Right, Val::Percent(50.0), Val::Auto, UiRect::left(Val::Px(SCOREBOARD_CENTER_GAP))),
+    ] {
+        commands.spawn((
+            PlayerNameUi(side),
+            Text::new(""),
+            TextFont {
+                font: scoreboard_font.clone(),
+                font_size: SETTING_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(15.0),
+                left,
+                right,
+                margin,
+                ..default()
+            },
+        ));
+    }
+
+    commands.spawn((
+        SpeedHudUi,
+        LocalizedFont,
+        Text::new(""),
+        TextFont {
+            font: scoreboard_font.clone(),
+            font_size: SETTING_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+
+    commands.spawn((
+        StateOverlayUi,
+        LocalizedFont,
+        Text::new(""),
+        TextFont {
+            font: scoreboard_font.clone(),
+            font_size: SETTING_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        GlobalZIndex(3),
+        Visibility::Hidden,
+    ));
+
+    commands.spawn((
+        LatencyHudUi,
+        LocalizedFont,
+        Text::new(""),
+        TextFont {
+            font: scoreboard_font.clone(),
+            font_size: SETTING_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+
+    spawn_input_overlay(&mut commands, scoreboard_font);
+
+    begin_match(&mut rng, &mut recording, &mut match_replay, *game_mode, false);
+    spawn_obstacles(commands, &modifiers, &mut rng, &arena, &obstacle_layout);
+}
+
+// 每局开始时回满生命，淘汰模式下在双方比分下方显示生命格
+fn spawn_health_bars(mut commands: Commands, modifiers: Res<Modifiers>, mut health: ResMut<Health>) {
+    *health = Health::default();
+    if !modifiers.elimination {
+        return;
+    }
+    for (side, left, right, margin) in [
+        (PaddleType::Left, Val::Auto, Val::Percent(50.0), UiRect::right(Val::Px(SCOREBOARD_CENTER_GAP))),
+        (PaddleType::Right, Val::Percent(50.0), Val::Auto, UiRect::left(Val::Px(SCOREBOARD_CENTER_GAP))),
+    ] {
+        commands
+            .spawn((
+                StateScoped(InMatch),
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(210.0),
+                    left,
+                    right,
+                    margin,
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                },
+            ))
+            .with_children(|row| {
+                for index in 0..STARTING_HEALTH {
+                    row.spawn((
+                        HealthPip { side, index },
+                        Node {
+                            width: Val::Px(16.0),
+                            height: Val::Px(16.0),
+                            ..default()
+                        },
+                        BackgroundColor(HEALTH_LOST_COLOR),
+                    ));
+                }
+            });
+    }
+}
+
+fn update_health_bars(health: Res<Health>, mut pips: Query<(&HealthPip, &mut BackgroundColor)>) {
+    for (pip, mut color) in &mut pips {
+        let lit = match pip.side {
+            PaddleType::Left => PLAYER_COLORS[0],
+            PaddleType::Right => PLAYER_COLORS[1],
+        };
+        let new_color = if pip.index < health.get(pip.side) { lit } else { HEALTH_LOST_COLOR };
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
+
+fn snapshot_match_state(progress: MatchProgress, mut pre_point: ResMut<PrePointState>) {
+    pre_point.0 = progress.snapshot();
+}
+
+fn record_point_history(
+    mut score_events: EventReader<ScoreEvent>,
+    pre_point: Res<PrePointState>,
+    mut point_history: ResMut<PointHistory>,
+) {
+    for event in score_events.read() {
+        let scorer = match event {
+            ScoreEvent::Player1Scored => PaddleType::Left,
+            ScoreEvent::Player2Scored => PaddleType::Right,
+        };
+        point_history.0.push((scorer, pre_point.0));
+    }
+}
+
+// 记录变化时重建整排圆点，颜色与进度条一致
+fn update_point_history_ui(
+    mut commands: Commands,
+    point_history: Res<PointHistory>,
+    container: Single<Entity, With<PointHistoryUi>>,
+) {
+    if !point_history.is_changed() {
+        return;
+    }
+    commands.entity(*container).despawn_related::<Children>().with_children(|row| {
+        for (side, _) in &point_history.0 {
+            let color = match side {
+                PaddleType::Left => PLAYER_COLORS[0],
+                PaddleType::Right => PLAYER_COLORS[1],
+            };
+            row.spawn((
+                Node {
+                    width: Val::Px(10.0),
+                    height: Val::Px(10.0),
+                    ..default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(color),
+            ));
+        }
+    });
+}
+
+// 比分数字贴着中线一侧，左方用 right 定位、右方用 left 定位，位数增加时向外侧增长
+fn scoreboard_node(side: PaddleType) -> Node {
+    let node = Node {
+        position_type: PositionType::Absolute,
+        top: Val::Px(45.0),
+        ..default()
+    };
+    match side {
+        PaddleType::Left => Node {
+            right: Val::Percent(50.0),
+            margin: UiRect::right(Val::Px(SCOREBOARD_CENTER_GAP)),
+            ..node
+        },
+        PaddleType::Right => Node {
+            left: Val::Percent(50.0),
+            margin: UiRect::left(Val::Px(SCOREBOARD_CENTER_GAP)),
+            ..node
+        },
+    }
+}
+
+fn update_scoreboard(
+    score: Res<Score>,
+    modifiers: Res<Modifiers>,
+    mut score_pop: ResMut<ScorePop>,
+    accessibility: Res<Accessibility>,
+    time: Res<Time>,
+    mut scoreboards: Query<(Entity, &ScoreboardUi, &mut Transform), (With<Text>, Without<VictoryText>)>,
+    mut progress_bars: Query<(&ScoreProgress, &mut Node)>,
+    mut writer: TextUiWriter,
+) {
+    // 差一分获胜（赛点）时填满半边
+    let match_point = modifiers.target_score.saturating_sub(1).max(1) as f32;
+    for (progress, mut node) in &mut progress_bars {
+        let points = match progress.0 {
+            PaddleType::Left => score.0,
+            PaddleType::Right => score.1,
+        };
+        let width = Val::Percent((points as f32 / match_point).min(1.0) * 100.0);
+        if node.width != width {
+            node.width = width;
+        }
+    }
+
+    // 按标记的一方取分数，不依赖查询的迭代顺序
+    let current = [score.0, score.1];
+    for (entity, scoreboard, mut transform) in &mut scoreboards {
+        let index = match scoreboard.0 {
+            PaddleType::Left => 0,
+            PaddleType::Right => 1,
+        };
+        *writer.text(entity, 1) = current[index].to_string();
+
+        let scored = current[index] > score_pop.previous[index];
+        let timer = &mut score_pop.timers[index];
+        if scored && !accessibility.reduce_motion {
+            timer.reset();
+        }
+        timer.tick(time.delta());
+        // 先放大再回落到原尺寸
+        let pop = (timer.fraction() * std::f32::consts::PI).sin();
+        transform.scale = Vec3::splat(1.0 + SCORE_POP_SCALE * pop);
+    }
+    score_pop.previous = current;
+}
+
+fn tick_serve_timer(mut serve_timer: ResMut<ServeTimer>, time: Res<Time>) {
+    serve_timer.0.tick(time.delta());
+}
+
+fn update_serve_countdown(serve_timer: Res<ServeTimer>, mut hud: Query<&mut Text, With<ServeCountdownUi>>) {
+    if !serve_timer.is_changed() {
+        return;
+    }
+    let step = SERVE_COUNTDOWN_SECS / 3.0;
+    let digit = (serve_timer.0.remaining_secs() / step).ceil() as u32;
+    for mut text in &mut hud {
+        **text = if digit == 0 { String::new() } else { digit.to_string() };
+    }
+}
+
+fn apply_velocity(
+    mut query: Query<(&mut Transform, &mut Velocity, Option<&mut Spin>, Has<Ball>)>,
+    serve_timer: Res<ServeTimer>,
+    time: Res<Time>,
+) {
+    for (mut transform, mut velocity, spin, is_ball) in &mut query {
+        // 倒计时期间主球停在发球点
+        if is_ball && serve_timer.counting_down() {
+            continue;
+        }
+        if let Some(mut spin) = spin {
+            **velocity = Vec2::from_angle(spin.0 * time.delta_secs()).rotate(**velocity);
+            spin.0 *= (-SPIN_DECAY * time.delta_secs()).exp();
+        }
+        transform.translation.x += velocity.x * time.delta_secs();
+        transform.translation.y += velocity.y * time.delta_secs();
+    }
+}
+
+// 回放比赛时按帧取出记录的输入，否则读取键盘并记录下来
+fn read_paddle_input(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    swap_sides: Res<SwapSides>,
+    controllers: Res<ControllerAssignment>,
+    gamepads: Query<&Gamepad>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera2d>>,
+    tournament: Res<TournamentMode>,
+    mut match_replay: ResMut<MatchReplay>,
+    mut recording: ResMut<MatchRecording>,
+    mut paddle_input: ResMut<PaddleInput>,
+    mut let_request: ResMut<LetRequest>,
+    mut let_key_held: Local<bool>,
+) {
+    let [left, right] = if match_replay.active {
+        let frame = recording.inputs.get(match_replay.cursor).copied().unwrap_or_default();
+        let_request.0 = recording.lets.contains(&match_replay.cursor);
+        match_replay.cursor += 1;
+        frame
+    } else {
+        // 固定帧里 just_pressed 可能漏掉或重复，自己判断按下的瞬间
+        let let_key_pressed = keyboard_input.pressed(KeyCode::Backspace);
+        let_request.0 = let_key_pressed && !*let_key_held;
+        *let_key_held = let_key_pressed;
+        if let_request.0 {
+            let tick = recording.inputs.len();
+            recording.lets.push(tick);
+        }
+
+        let read = |keys: &PaddleKeys| {
+            let mut direction = 0.0;
+            if keyboard_input.pressed(keys.up) {
+                direction += 1.0;
+            }
+            if keyboard_input.pressed(keys.down) {
+                direction -= 1.0;
+            }
+            PaddleCommand {
+                direction,
+                boost: keyboard_input.pressed(keys.boost) && !tournament.0,
+                block: keyboard_input.pressed(keys.block),
+                target: None,
+            }
+        };
+        // 摇杆和十字键都能移动，右肩键（RB/R1）加速，左肩键格挡
+        let read_gamepad = |gamepad: &Gamepad| {
+            let stick = gamepad.left_stick().y;
+            let mut direction = if stick.abs() > GAMEPAD_DEADZONE { stick.signum() } else { 0.0 };
+            if gamepad.pressed(GamepadButton::DPadUp) {
+                direction = 1.0;
+            }
+            if gamepad.pressed(GamepadButton::DPadDown) {
+                direction = -1.0;
+            }
+            PaddleCommand {
+                direction,
+                boost: gamepad.pressed(GamepadButton::RightTrigger) && !tournament.0,
+                block: gamepad.pressed(GamepadButton::LeftTrigger),
+                target: None,
+            }
+        };
+        // 光标在窗口外时没有目标，挡板停在原处
+        let (camera, camera_transform) = *camera;
+        let cursor_y = window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+            .map(|cursor| cursor.y);
+        let mut frame = [read(&key_bindings.left), read(&key_bindings.right)];
+        if swap_sides.0 {
+            frame.swap(0, 1);
+        }
+        // 分配了手柄的一方键盘仍然有效，两者任一有输入即可；鼠标控制时忽略键盘
+        for (command, side) in frame.iter_mut().zip([PaddleType::Left, PaddleType::Right]) {
+            match controllers.get(side) {
+                InputSource::Gamepad(entity) => {
+                    if let Ok(gamepad) = gamepads.get(entity) {
+                        let pad = read_gamepad(gamepad);
+                        *command = PaddleCommand {
+                            direction: if pad.direction != 0.0 { pad.direction } else { command.direction },
+                            boost: pad.boost || command.boost,
+                            block: pad.block || command.block,
+                            target: None,
+                        };
+                    }
+                }
+                InputSource::Mouse => {
+                    *command = PaddleCommand {
+                        direction: 0.0,
+                        boost: false,
+                        block: mouse_buttons.pressed(MouseButton::Left),
+                        target: cursor_y,
+                    };
+                }
+                InputSource::Keyboard => {}
+            }
+        }
+        recording.inputs.push(frame);
+        frame
+    };
+    *paddle_input = PaddleInput { left, right };
+}
+
+// 鼠标的目标位置也不瞬移：每帧最多移动键盘控制时的距离，
+// 否则由位移推出的挡板速度会暴涨，搓球和动量传递随之失控
+fn next_paddle_position(old: f32, command: &PaddleCommand, max_step: f32) -> f32 {
+    match command.target {
+        Some(target) => old + (target - old).clamp(-max_step, max_step),
+        None => old + command.direction * max_step,
+    }
+}
+
+fn move_paddle(
+    paddle_input: Res<PaddleInput>,
+    paddle_travel: Res<PaddleTravelSettings>,
+    mut query: Query<(&mut Transform, &mut PaddleVelocity, &mut Blocking, &PaddleType), With<Paddle>>,
+    time: Res<Time>,
+) {
+    for (mut paddle_transform, mut paddle_velocity, mut blocking, paddle_type) in query.iter_mut(){
+        let (bottom_bound, top_bound) = paddle_travel.get(*paddle_type).bounds();
+        let command = match paddle_type {
+            PaddleType::Left => paddle_input.left,
+            PaddleType::Right => paddle_input.right,
+        };
+        let mut accelerate_fact = if command.boost { 2.0 } else { 1.0 };
+        blocking.0 = command.block;
+        if blocking.0 {
+            accelerate_fact *= BLOCK_MOVE_FACTOR;
+        }
+        let old_paddle_position = paddle_transform.translation.y;
+        let max_step = PADDLE_SPEED * accelerate_fact * time.delta_secs();
+        let new_paddle_position = next_paddle_position(old_paddle_position, &command, max_step);
+        paddle_transform.translation.y = new_paddle_position.clamp(bottom_bound, top_bound);
+        paddle_velocity.0 = (paddle_transform.translation.y - old_paddle_position) / time.delta_secs();
+    }
+}
+
+// 接球模式中，挡板按住格挡键时碰到球就把球接住
+fn catch_ball(
+    mut commands: Commands,
+    modifiers: Res<Modifiers>,
+    mut collision_events: EventReader<CollisionEvent>,
+    ball_query: Single<(Entity, &mut Velocity, &mut Spin, &Transform, Has<HeldBall>), With<Ball>>,
+    paddle_query: Query<(&Transform, &PaddleType, &Blocking), With<Paddle>>,
+) {
+    let (ball, mut ball_velocity, mut ball_spin, ball_transform, already_held) = ball_query.into_inner();
+    if !modifiers.catch || already_held {
+        collision_events.clear();
+        return;
+    }
+    for event in collision_events.read() {
+        let ColliderKind::Paddle(side) = event.kind else {
+            continue;
+        };
+        let Some((paddle_transform, _, _)) = paddle_query
+            .iter()
+            .find(|(_, paddle_type, blocking)| **paddle_type == side && blocking.0)
+        else {
+            continue;
+        };
+        let offset = (ball_transform.translation.y - paddle_transform.translation.y)
+            .clamp(-PADDLE_SIZE.y / 2.0, PADDLE_SIZE.y / 2.0);
+        commands.entity(ball).insert(HeldBall {
+            by: side,
+            offset,
+            speed: ball_velocity.length(),
+            timer: Timer::from_seconds(CATCH_HOLD_SECS, TimerMode::Once),
+        });
+        **ball_velocity = Vec2::ZERO;
+        ball_spin.0 = 0.0;
+        return;
+    }
+}
+
+// 持球时把球贴在挡板前面；松开格挡键或超时后按挡板的高度决定掷球角度：
+// 挡板越靠上，球越往上飞
+fn carry_held_ball(
+    mut commands: Commands,
+    mut ball_query: Query<(Entity, &mut HeldBall, &mut Velocity, &mut Transform), With<Ball>>,
+    paddle_query: Query<(&Transform, &PaddleType, &Blocking), (With<Paddle>, Without<Ball>)>,
+    time: Res<Time>,
+) {
+    let Ok((ball, mut held, mut ball_velocity, mut ball_transform)) = ball_query.single_mut() else {
+        return;
+    };
+    let Some((paddle_transform, _, blocking)) =
+        paddle_query.iter().find(|(_, paddle_type, _)| **paddle_type == held.by)
+    else {
+        return;
+    };
+    let inward = -held.by.side();
+    ball_transform.translation.x =
+        paddle_transform.translation.x + inward * (PADDLE_SIZE.x / 2.0 + BALL_SIZE / 2.0 + 1.0);
+    ball_transform.translation.y = paddle_transform.translation.y + held.offset;
+
+    if held.timer.tick(time.delta()).finished() || !blocking.0 {
+        let (_, top_bound) = paddle_bounds();
+        let aim = (paddle_transform.translation.y / top_bound).clamp(-1.0, 1.0);
+        let direction = Vec2::from_angle(aim * MAX_THROW_ANGLE) * Vec2::new(inward, 1.0);
+        **ball_velocity = direction * held.speed;
+        commands.entity(ball).remove::<HeldBall>();
+    }
+}
+
+// 挡板中心可移动的范围 (下界, 上界)
+fn paddle_bounds() -> (f32, f32) {
+    let edge = VERTICAL_WALL_THICKNESS / 2.0 + PADDLE_EDGE_MARGIN + PADDLE_SIZE.y / 2.0;
+    (BOTTOM_WALL + edge, TOP_WALL - edge)
+}
+
+fn check_for_collisions(
+    mut score: ResMut<Score>,
+    mut health: ResMut<Health>,
+    mut winner: ResMut<Winner>,
+    mut next_state: ResMut<NextState<GameState>>,
+    modifiers: Res<Modifiers>,
+    mut serve: ResMut<Serve>,
+    grace_period: Res<GracePeriod>,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), With<Ball>>,
+    collider_grid: Res<ColliderGrid>,
+    collider_query: Query<
+        (
+            &Transform,
+            Option<&WallType>,
+            Option<&PaddleType>,
+            Option<&PaddleVelocity>,
+            Option<&Blocking>,
+            Option<&Restitution>,
+            Option<&HitSound>,
+            Option<&PhaseChance>,
+        ),
+        With<Collider>,
+    >,
+    mut collision_events: EventWriter<CollisionEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    split_balls: Query<(), With<SplitBall>>,
+    mut phasing_through: Local<Option<Entity>>,
+) {
+    let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+    let incoming_speed = ball_velocity.length();
+
+    if ball_transform.translation.x * serve.side < 0.0 {
+        serve.crossed_net = true;
+    }
+    // 未过网时球门按普通墙壁反弹
+    let scoring_allowed = serve.crossed_net || !modifiers.serve_must_clear_net;
+
+    let ball_box = Aabb2d::new(ball_transform.translation.truncate(), Vec2::splat(BALL_SIZE / 2.));
+    let mut still_phasing = false;
+    for entity in collider_grid.nearby(ball_box) {
+        let Ok((
+            collider_transform,
+            maybe_wall_type,
+            maybe_paddle,
+            maybe_paddle_velocity,
+            maybe_blocking,
+            maybe_restitution,
+            maybe_hit_sound,
+            maybe_phase_chance,
+        )) = collider_query.get(entity)
+        else {
+            continue;
+        };
+        let sound = maybe_hit_sound.map(|hit_sound| hit_sound.0.clone());
+        let ball = BoundingCircle::new(ball_transform.translation.truncate(), BALL_SIZE / 2.);
+
+        if let Some((collision, contact)) = collider_collision(ball, collider_transform) {
+            // 每次接触只掷一次：决定穿过后，直到球离开该障碍都不再反弹
+            if let Some(phase_chance) = maybe_phase_chance {
+                if *phasing_through == Some(entity) {
+                    still_phasing = true;
+                    continue;
+                }
+                if rng.random_bool(phase_chance.0 as f64) {
+                    *phasing_through = Some(entity);
+                    still_phasing = true;
+                    continue;
+                }
+            }
+            if let Some(wall_type) = maybe_wall_type {
+                match wall_type {
+                    // 热身期间进球不计分，按原样重新发球
+                    WallType::Left | WallType::Right if scoring_allowed && grace_period.active() => {
+                        **ball_velocity = serve.velocity;
+                        ball_spin.0 = 0.0;
+                        ball_transform.translation = serve.position;
+                        *serve = Serve::new(serve.position, serve.velocity);
+                        return;
+                    }
+                    WallType::Right if scoring_allowed => {
+                        award_point(PaddleType::Left, &modifiers, &mut score, &mut health, &mut winner, &mut next_state, &mut score_events);
+                        continue;
+                    }
+                    WallType::Left if scoring_allowed => {
+                        award_point(PaddleType::Right, &modifiers, &mut score, &mut health, &mut winner, &mut next_state, &mut score_events);
+                        continue;
+                    }
+                    _ => {
+                        collision_events.write(CollisionEvent {
+                            position: contact,
+                            speed: incoming_speed,
+                            side: collision,
+                            kind: ColliderKind::Wall(*wall_type),
+                            collider: entity,
+                            sound,
+                        });
+                        // 只在球朝墙运动（即将反弹）时判定，避免贴墙的几帧里重复分裂
+                        let approaching = match wall_type {
+                            WallType::Top => ball_velocity.y > 0.0,
+                            WallType::Bottom => ball_velocity.y < 0.0,
+                            WallType::Left | WallType::Right => false,
+                        };
+                        // 竖直速度衰减后仍不低于最低球速
+                        if approaching && modifiers.ball.wall_damping < 1.0 {
+                            ball_velocity.y *= modifiers.ball.wall_damping;
+                            if ball_velocity.length() < modifiers.ball.min_speed {
+                                **ball_velocity = ball_velocity.normalize_or_zero() * modifiers.ball.min_speed;
+                            }
+                        }
+                        if approaching
+                            && modifiers.ball_split_chance > 0.0
+                            && split_balls.iter().len() < MAX_SPLIT_BALLS
+                            && rng.random_bool(modifiers.ball_split_chance as f64)
+                        {
+                            let angle = rng.random_range(-0.4..=0.4);
+                            let reflected = Vec2::new(ball_velocity.x, -ball_velocity.y);
+                            spawn_split_ball(&mut commands, ball_transform.translation, Vec2::from_angle(angle).rotate(reflected));
+                        }
+                    }
+                }
+            } else{
+                collision_events.write(CollisionEvent {
+                    position: contact,
+                    speed: incoming_speed,
+                    side: collision,
+                    kind: maybe_paddle.map_or(ColliderKind::Obstacle, |paddle| ColliderKind::Paddle(*paddle)),
+                    collider: entity,
+                    sound,
+                });
+            }
+
+            if let Some(paddle_velocity) = maybe_paddle_velocity {
+                // 搓球：击球瞬间挡板的移动方向决定弧线方向
+                let facing = maybe_paddle.map_or(1.0, |paddle| -paddle.side());
+                ball_spin.0 = paddle_velocity.0 * modifiers.ball.curve_strength * facing;
+                match modifiers.ball.momentum_transfer {
+                    // 挡板与球的竖直运动同向时加速，反向时减速
+                    Some(transfer) => {
+                        let speed = ball_velocity.length()
+                            + transfer * paddle_velocity.0 * ball_velocity.y.signum();
+                        **ball_velocity = ball_velocity.normalize()
+                            * speed.clamp(modifiers.ball.min_speed, modifiers.ball.max_speed);
+                    }
+                    // 默认每次接球加速1.1倍
+                    None => {
+                        **ball_velocity = sped_up_velocity(**ball_velocity, &modifiers.ball);
+                    }
+                }
+                if maybe_blocking.is_some_and(|blocking| blocking.0) {
+                    let speed = ball_velocity.length() * modifiers.ball.block_dampening;
+                    **ball_velocity = ball_velocity.normalize() * speed.max(modifiers.ball.min_speed);
+                }
+            }
+            
+            if let Some(restitution) = maybe_restitution {
+                reflect_velocity(&mut ball_velocity, collision, restitution.0);
+                **ball_velocity = ball_velocity.clamp_length(modifiers.ball.min_speed, modifiers.ball.max_speed);
+            } else {
+                reflect_velocity(&mut ball_velocity, collision, 1.0);
+            }
+
+            // 击中挡板正面时按击球点离中心的距离决定出射角，只改方向，球速保持加速后的大小
+            if let (Some(paddle), Collision::Left | Collision::Right) = (maybe_paddle, collision) {
+                **ball_velocity = paddle_bounce_velocity(
+                    *paddle,
+                    collider_transform,
+                    ball_transform.translation.y,
+                    ball_velocity.length(),
+                );
+            }
+
+            // 偏转后方向反转或过于竖直时保持原来的反弹方向
+            let jitter = modifiers.ball.reflection_jitter;
+            if maybe_paddle.is_some() && jitter > 0.0 {
+                let jittered = Vec2::from_angle(rng.random_range(-jitter..=jitter)).rotate(**ball_velocity);
+                if jittered.x.signum() == ball_velocity.x.signum()
+                    && jittered.y.atan2(jittered.x.abs()).abs() <= MAX_JITTER_BOUNCE_ANGLE
+                {
+                    **ball_velocity = jittered;
+                }
+            }
+        }
+    }
+    if !still_phasing {
+        *phasing_through = None;
+    }
+}
+
+// 给一方加一分，达到目标分数时先定格，再结束比赛；无尽模式只计分
+fn award_point(
+    scorer: PaddleType,
+    modifiers: &Modifiers,
+    score: &mut Score,
+    health: &mut Health,
+    winner: &mut Winner,
+    next_state: &mut NextState<GameState>,
+    score_events: &mut EventWriter<ScoreEvent>,
+) {
+    let (points, opponent_points) = match scorer {
+        PaddleType::Left => {
+            score_events.write(ScoreEvent::Player1Scored);
+            score.0 += 1;
+            (score.0, score.1)
+        }
+        PaddleType::Right => {
+            score_events.write(ScoreEvent::Player2Scored);
+            score.1 += 1;
+            (score.1, score.0)
+        }
+    };
+    if modifiers.elimination {
+        let remaining = health.get_mut(scorer.opponent());
+        *remaining = remaining.saturating_sub(1);
+        if *remaining == 0 {
+            winner.0 = Some(scorer);
+            next_state.set(GameState::WinFreeze);
+        }
+    } else if wins_match(points, opponent_points, modifiers) {
+        winner.0 = Some(scorer);
+        next_state.set(GameState::WinFreeze);
+    }
+}
+
+// 得分方加分后是否赢下比赛。领先两分的规则下，双方都过了目标分数也要继续打，直到拉开两分
+fn wins_match(points: usize, opponent_points: usize, modifiers: &Modifiers) -> bool {
+    let required_lead = if modifiers.win_by_two { 2 } else { 1 };
+    points >= modifiers.target_score && points >= opponent_points + required_lead && !modifiers.endless
+}
+
+// 只在球正朝碰撞面运动时反弹，避免球卡在碰撞体里来回翻转
+fn reflect_velocity(velocity: &mut Vec2, collision: Collision, restitution: f32) {
+    let mut reflect_x = false;
+    let mut reflect_y = false;
+
+    match collision {
+        Collision::Left => reflect_x = velocity.x > 0.0,
+        Collision::Right => reflect_x = velocity.x < 0.0,
+        Collision::Top => reflect_y = velocity.y < 0.0,
+        Collision::Bottom => reflect_y = velocity.y > 0.0,
+        // 沿法线反射，恢复系数只作用于法向分量
+        Collision::Oriented(normal) => {
+            let along = velocity.dot(normal);
+            if along < 0.0 {
+                *velocity -= (1.0 + restitution) * along * normal;
+            }
+            return;
+        }
+    }
+
+    if reflect_x {
+        velocity.x = -velocity.x * restitution;
+    }
+    if reflect_y {
+        velocity.y = -velocity.y * restitution;
+    }
+}
+
+// 每次成功接球后，球速按倍率增加，方向不变地限制在上限以内
+fn sped_up_velocity(velocity: Vec2, ball: &BallDynamics) -> Vec2 {
+    (velocity * ball.paddle_speed_up).clamp_length_max(ball.max_speed)
+}
+
+// 挡板的实际高度取自 scale（挡板缩放道具会改变它），击球点越靠边出射角越大
+fn paddle_bounce_velocity(paddle: PaddleType, paddle_transform: &Transform, ball_y: f32, speed: f32) -> Vec2 {
+    let offset = (ball_y - paddle_transform.translation.y) / (paddle_transform.scale.y / 2.0);
+    let angle = offset.clamp(-1.0, 1.0) * MAX_PADDLE_BOUNCE_ANGLE;
+    Vec2::new(-paddle.side() * angle.cos(), angle.sin()) * speed
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Collision {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Oriented(Vec2), // 旋转的碰撞体，值为从碰撞体指向球的单位法线
+}
+
+// 碰撞体的位置和绕 z 轴的旋转
+fn collider_isometry(transform: &Transform) -> Isometry2d {
+    let (angle, _, _) = transform.rotation.to_euler(EulerRot::ZYX);
+    Isometry2d::new(transform.translation.truncate(), Rot2::radians(angle))
+}
+
+// 包含碰撞体的轴对齐包围盒，用于宽相位网格
+fn collider_bounds(transform: &Transform) -> Aabb2d {
+    Rectangle::from_size(transform.scale.truncate()).aabb_2d(collider_isometry(transform))
+}
+
+// 高速时球在一个固定帧内可能整个越过薄挡板：沿本帧的移动线段扫掠，
+// 途中先碰到碰撞体时把球退回首次接触处，由碰撞检测照常反弹。主球和额外的球都要扫掠
+fn sweep_ball(
+    mut ball_query: Query<
+        (&Velocity, &mut Transform, Has<Ball>),
+        (Or<(With<Ball>, With<ExtraBall>)>, Without<HeldBall>),
+    >,
+    collider_grid: Res<ColliderGrid>,
+    collider_query: Query<&Transform, (With<Collider>, Without<Ball>, Without<ExtraBall>)>,
+    serve_timer: Res<ServeTimer>,
+    time: Res<Time>,
+) {
+    let radius = BALL_SIZE / 2.;
+    for (ball_velocity, mut ball_transform, is_main_ball) in &mut ball_query {
+        // 倒计时期间主球没有移动，按速度反推的起点不成立
+        if is_main_ball && serve_timer.counting_down() {
+            continue;
+        }
+        let current = ball_transform.translation.truncate();
+        let previous = current - **ball_velocity * time.delta_secs();
+        let swept = Aabb2d::new((previous + current) / 2., (current - previous).abs() / 2. + radius);
+        let colliders = collider_grid
+            .nearby(swept)
+            .into_iter()
+            .filter_map(|entity| collider_query.get(entity).ok());
+        if let Some(contact) = swept_contact(previous, current, radius, colliders) {
+            ball_transform.translation = contact.extend(ball_transform.translation.z);
+        }
+    }
+}
+
+// 球从 previous 移到 current 途中首次接触碰撞体的位置，略微嵌入以便碰撞检测触发。
+// 旋转的碰撞体把线段变换到它的局部坐标系里再做 AABB 扫掠，刚体变换不改变沿线段的距离
+fn swept_contact<'a>(
+    previous: Vec2,
+    current: Vec2,
+    radius: f32,
+    colliders: impl IntoIterator<Item = &'a Transform>,
+) -> Option<Vec2> {
+    let direction = Dir2::new(current - previous).ok()?;
+    let travel = previous.distance(current);
+    let start = BoundingCircle::new(previous, radius);
+    let ball_box = Aabb2d::new(Vec2::ZERO, Vec2::splat(radius));
+
+    let first_contact = colliders
+        .into_iter()
+        // 起点已经接触的碰撞体（例如正在穿过的幽灵障碍）不参与扫掠
+        .filter(|transform| collider_collision(start, transform).is_none())
+        .filter_map(|transform| {
+            let isometry = collider_isometry(transform);
+            let local_direction = isometry.rotation.inverse() * direction;
+            let cast = AabbCast2d::new(ball_box, isometry.inverse_transform_point(previous), local_direction, travel);
+            cast.aabb_collision_at(Aabb2d::new(Vec2::ZERO, transform.scale.truncate() / 2.))
+        })
+        .min_by(f32::total_cmp)?;
+    Some(previous + direction * (first_contact + SWEEP_OVERLAP).min(travel))
+}
+
+// 未旋转的碰撞体走 AABB 快速路径，旋转的按有向包围盒检测；同时返回接触点
+fn collider_collision(ball: BoundingCircle, transform: &Transform) -> Option<(Collision, Vec2)> {
+    let isometry = collider_isometry(transform);
+    let half_size = transform.scale.truncate() / 2.;
+    if isometry.rotation.as_radians().abs() < 1e-4 {
+        let bounding_box = Aabb2d::new(isometry.translation, half_size);
+        let collision = ball_collision(ball, bounding_box)?;
+        return Some((collision, bounding_box.closest_point(ball.center())));
+    }
+    ball_collision_oriented(ball, isometry, half_size)
+}
+
+// 圆与有向盒的分离轴检测：把球心变换到盒子的局部坐标系，
+// 问题就变成圆与 AABB，最近点到球心的方向即为分离轴
+fn ball_collision_oriented(
+    ball: BoundingCircle,
+    isometry: Isometry2d,
+    half_size: Vec2,
+) -> Option<(Collision, Vec2)> {
+    let local = isometry.inverse_transform_point(ball.center());
+    let closest = local.clamp(-half_size, half_size);
+    let offset = local - closest;
+    if offset.length_squared() > ball.radius() * ball.radius() {
+        return None;
+    }
+
+    let local_normal = if offset == Vec2::ZERO {
+        // 球心已经进入盒内：沿穿透最浅的轴推出
+        let depth = half_size - local.abs();
+        if depth.x < depth.y {
+            Vec2::new(local.x.signum(), 0.0)
+        } else {
+            Vec2::new(0.0, local.y.signum())
+        }
+    } else {
+        offset.normalize()
+    };
+    Some((
+        Collision::Oriented(isometry.rotation * local_normal),
+        isometry.transform_point(closest),
+    ))
+}
+
+// 按球心到最近点的偏移判断撞在哪一面；水平和竖直偏移相等（正好撞在角上）时算作上下面
+fn ball_collision(ball: BoundingCircle, bounding_box: Aabb2d) -> Option<Collision> {
+    if !ball.intersects(&bounding_box) {
+        return None;
+    }
+
+    let closest = bounding_box.closest_point(ball.center());
+    let offset = ball.center() - closest;
+    let side = if offset.x.abs() > offset.y.abs() {
+        if offset.x < 0. {
+            Collision::Left
+        } else {
+            Collision::Right
+        }
+    } else if offset.y > 0. {
+        Collision::Top
+    } else {
+        Collision::Bottom
+    };
+
+    Some(side)
+}
+
+fn toggle_debug_colliders(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut debug_colliders: ResMut<DebugColliders>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        debug_colliders.0 = !debug_colliders.0;
+    }
+}
+
+// 两侧各一排指示灯，放在底部角落，默认隐藏
+fn spawn_input_overlay(commands: &mut Commands, font: Handle<Font>) {
+    let inputs = [InputKind::Up, InputKind::Down, InputKind::Boost, InputKind::Block];
+    for side in [PaddleType::Left, PaddleType::Right] {
+        let (left, right) = match side {
+            PaddleType::Left => (Val::Px(10.0), Val::Auto),
+            PaddleType::Right => (Val::Auto, Val::Px(10.0)),
+        };
+        commands
+            .spawn((
+                InputOverlayUi,
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(40.0),
+                    left,
+                    right,
+                    column_gap: Val::Px(12.0),
+                    ..default()
+                },
+                Visibility::Hidden,
+            ))
+            .with_children(|row| {
+                for input in inputs {
+                    row.spawn((
+                        InputIndicator { side, input },
+                        Text::new(input.label()),
+                        TextFont {
+                            font: font.clone(),
+                            font_size: SETTING_FONT_SIZE,
+                            ..default()
+                        },
+                        TextColor(INPUT_IDLE_COLOR),
+                    ));
+                }
+            });
+    }
+}
+
+fn toggle_input_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut input_overlay: ResMut<InputOverlay>,
+    mut overlay_query: Query<&mut Visibility, With<InputOverlayUi>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F2) {
+        return;
+    }
+    input_overlay.0 = !input_overlay.0;
+    for mut visibility in &mut overlay_query {
+        *visibility = if input_overlay.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+// 读取 PaddleInput，比赛回放时显示的也是回放中的输入
+fn update_input_overlay(
+    input_overlay: Res<InputOverlay>,
+    paddle_input: Res<PaddleInput>,
+    mut indicator_query: Query<(&InputIndicator, &mut TextColor)>,
+) {
+    if !input_overlay.0 {
+        return;
+    }
+    for (indicator, mut color) in &mut indicator_query {
+        let command = match indicator.side {
+            PaddleType::Left => &paddle_input.left,
+            PaddleType::Right => &paddle_input.right,
+        };
+        color.0 = if indicator.input.is_active(command) { INPUT_ACTIVE_COLOR } else { INPUT_IDLE_COLOR };
+    }
+}
+
+fn toggle_speed_hud(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut speed_hud: ResMut<SpeedHud>,
+    mut hud_query: Query<&mut Visibility, With<SpeedHudUi>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F3) {
+        return;
+    }
+    speed_hud.0 = !speed_hud.0;
+    for mut visibility in &mut hud_query {
+        *visibility = if speed_hud.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+fn toggle_latency_probe(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut latency_probe: ResMut<LatencyProbe>,
+    mut hud_query: Query<&mut Visibility, With<LatencyHudUi>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F5) {
+        return;
+    }
+    *latency_probe = LatencyProbe {
+        active: !latency_probe.active,
+        ..default()
+    };
+    for mut visibility in &mut hud_query {
+        *visibility = if latency_probe.active { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+// 与 read_paddle_input 相同的按键映射，松开按键时放弃这次测量
+fn probe_key_presses(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    swap_sides: Res<SwapSides>,
+    mut latency_probe: ResMut<LatencyProbe>,
+) {
+    if !latency_probe.active {
+        return;
+    }
+    let mut keys = [key_bindings.left, key_bindings.right];
+    if swap_sides.0 {
+        keys.swap(0, 1);
+    }
+    for (index, keys) in keys.iter().enumerate() {
+        if keyboard_input.any_just_pressed([keys.up, keys.down]) {
+            latency_probe.pressed_at[index] = Some(Instant::now());
+        } else if !keyboard_input.any_pressed([keys.up, keys.down]) {
+            latency_probe.pressed_at[index] = None;
+        }
+    }
+}
+
+fn measure_paddle_latency(
+    mut latency_probe: ResMut<LatencyProbe>,
+    paddle_query: Query<(&PaddleType, &PaddleVelocity), With<Paddle>>,
+) {
+    if !latency_probe.active {
+        return;
+    }
+    for (paddle_type, paddle_velocity) in &paddle_query {
+        let index = match paddle_type {
+            PaddleType::Left => 0,
+            PaddleType::Right => 1,
+        };
+        if paddle_velocity.0 == 0.0 {
+            continue;
+        }
+        if let Some(pressed_at) = latency_probe.pressed_at[index].take() {
+            let ms = pressed_at.elapsed().as_secs_f32() * 1000.0;
+            info!("input latency ({}): {ms:.1} ms", if index == 0 { "left" } else { "right" });
+            latency_probe.last_ms[index] = Some(ms);
+        }
+    }
+}
+
+fn update_latency_hud(
+    latency_probe: Res<LatencyProbe>,
+    locale: Res<Locale>,
+    mut hud_query: Query<&mut Text, With<LatencyHudUi>>,
+) {
+    if !latency_probe.active || (!latency_probe.is_changed() && !locale.is_changed()) {
+        return;
+    }
+    let format_ms = |ms: Option<f32>| ms.map_or("--".to_string(), |ms| format!("{ms:.1}"));
+    for mut text in &mut hud_query {
+        **text = format!(
+            "{} {} / {} {}",
+            locale.text(TextKey::Latency),
+            format_ms(latency_probe.last_ms[0]),
+            format_ms(latency_probe.last_ms[1]),
+            locale.text(TextKey::Milliseconds)
+        );
+    }
+}
+
+fn update_speed_hud(
+    speed_hud: Res<SpeedHud>,
+    modifiers: Res<Modifiers>,
+    locale: Res<Locale>,
+    ball_query: Single<&Velocity, With<Ball>>,
+    mut hud_query: Query<&mut Text, With<SpeedHudUi>>,
+) {
+    if !speed_hud.0 {
+        return;
+    }
+    for mut text in &mut hud_query {
+        **text = format!(
+            "{} {:.0} / {:.0}",
+            locale.text(TextKey::Speed),
+            ball_query.length(),
+            modifiers.ball.max_speed
+        );
+    }
+}
+
+fn toggle_state_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut state_overlay: ResMut<StateOverlay>,
+    mut overlay_query: Query<&mut Visibility, With<StateOverlayUi>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F6) {
+        return;
+    }
+    state_overlay.0 = !state_overlay.0;
+    for mut visibility in &mut overlay_query {
+        *visibility = if state_overlay.0 { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+// 修正项是最近一局开始时推导的，在菜单中显示的是上一局的设置
+fn update_state_overlay(
+    state_overlay: Res<StateOverlay>,
+    state: Res<State<GameState>>,
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    modifiers: Res<Modifiers>,
+    roulette: Res<ModifierRoulette>,
+    locale: Res<Locale>,
+    mut overlay_query: Query<&mut Text, With<StateOverlayUi>>,
+) {
+    if !state_overlay.0 {
+        return;
+    }
+    let locale = *locale;
+    let mut active = modifiers.active_labels(locale);
+    if let Some(modifier) = roulette.chosen {
+        active.push(format!("{} {}", locale.text(TextKey::Roulette), modifier.label(locale)));
+    }
+    let active = if active.is_empty() { locale.text(TextKey::NoModifiers).to_string() } else { active.join(", ") };
+    let new_text = format!(
+        "{} {:?}\n{} {}\n{} {}\n{} {active}",
+        locale.text(TextKey::State),
+        state.get(),
+        locale.text(TextKey::Mode),
+        game_mode.label(locale),
+        locale.text(TextKey::Difficulty),
+        difficulty.label(locale),
+        locale.text(TextKey::ActiveModifiers),
+    );
+    for mut text in &mut overlay_query {
+        if **text != new_text {
+            **text = new_text.clone();
+        }
+    }
+}
+
+fn toggle_velocity_arrow(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut velocity_arrow: ResMut<VelocityArrow>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        velocity_arrow.0 = !velocity_arrow.0;
+    }
+}
+
+// 回放时真正的球被隐藏，箭头也不画
+fn draw_velocity_arrow(
+    velocity_arrow: Res<VelocityArrow>,
+    mut gizmos: Gizmos,
+    ball_query: Single<(&Transform, &Velocity, &Visibility), With<Ball>>,
+) {
+    let (ball_transform, ball_velocity, visibility) = *ball_query;
+    if !velocity_arrow.0 || *visibility == Visibility::Hidden {
+        return;
+    }
+    let start = ball_transform.translation.truncate();
+    gizmos.arrow_2d(
+        start,
+        start + **ball_velocity * VELOCITY_ARROW_SECS,
+        Color::srgb(1.0, 1.0, 0.3),
+    );
+}
+
+// 与 check_for_collisions 使用相同的包围盒和包围圆
+fn draw_collider_gizmos(
+    debug_colliders: Res<DebugColliders>,
+    mut gizmos: Gizmos,
+    collider_query: Query<&Transform, With<Collider>>,
+    ball_query: Query<&Transform, With<Ball>>,
+) {
+    if !debug_colliders.0 {
+        return;
+    }
+    for collider_transform in &collider_query {
+        gizmos.rect_2d(
+            collider_isometry(collider_transform),
+            collider_transform.scale.truncate(),
+            Color::srgb(0.0, 1.0, 0.0),
+        );
+    }
+    for ball_transform in &ball_query {
+        gizmos.circle_2d(
+            ball_transform.translation.truncate(),
+            BALL_SIZE / 2.,
+            Color::srgb(1.0, 0.0, 0.0),
+        );
+    }
+}
+
+fn play_collision_sound(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut score_events: EventReader<ScoreEvent>,
+    collision_sound: Res<CollisionSound>,
+    score_sound: Res<ScoreSound>,
+    audio_settings: Res<AudioSettings>,
+    mut last_collision_sound: ResMut<LastCollisionSound>,
+    time: Res<Time>,
+) {
+    let hit = !collision_events.is_empty();
+    let mut hit_paddle = None;
+    let mut hit_sound = None;
+    let mut hit_speed: f32 = 0.0;
+    for event in collision_events.read() {
+        hit_speed = hit_speed.max(event.speed);
+        if let ColliderKind::Paddle(paddle) = event.kind {
+            hit_paddle = Some(paddle);
+        }
+        hit_sound = event.sound.clone().or(hit_sound);
+    }
+    // 撞墙、撞障碍的声音在冷却时间内不重复播放，挡板击球总是播放
+    let now = time.elapsed();
+    let cooling_down = last_collision_sound
+        .0
+        .is_some_and(|last| (now - last).as_secs_f32() < COLLISION_SOUND_COOLDOWN_SECS);
+    if hit && (hit_paddle.is_some() || !cooling_down) {
+        last_collision_sound.0 = Some(now);
+        let sound = hit_sound.unwrap_or_else(|| collision_sound.clone());
+        // 球越快音调越高，初始球速时为原音高
+        let pitch = (hit_speed / BALL_SPEED).clamp(MIN_COLLISION_PITCH, MAX_COLLISION_PITCH);
+        let playback = PlaybackSettings::DESPAWN.with_speed(pitch);
+        match hit_paddle {
+            // 挡板击球时声音偏向该挡板一侧
+            Some(paddle) => {
+                commands.spawn((
+                    AudioPlayer(sound),
+                    playback.with_spatial(true),
+                    Transform::from_xyz(paddle.side() * audio_settings.pan_strength, 0.0, 0.0),
+                ));
+            }
+            None => {
+                commands.spawn((AudioPlayer(sound), playback));
+            }
+        }
+    }
+    for event in score_events.read() {
+        // 两名玩家的得分音高略有不同，听声音就能分辨谁得分
+        let speed = match event {
+            ScoreEvent::Player1Scored => 1.06,
+            ScoreEvent::Player2Scored => 0.94,
+        };
+        commands.spawn((
+            AudioPlayer(score_sound.clone()),
+            PlaybackSettings::DESPAWN.with_speed(speed),
+        ));
+    }
+}
+
+fn count_bounces(
+    mut commands: Commands,
+    mut bounce_count: ResMut<BounceCount>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut score_events: EventReader<ScoreEvent>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+) {
+    bounce_count.0 += collision_events.read().count();
+
+    if score_events.read().count() > 0 {
+        // 得分后短暂显示本回合的反弹次数
+        if bounce_count.0 > 0 {
+            commands.spawn((
+                StateScoped(InMatch),
+                BounceMessage(Timer::from_seconds(BOUNCE_MESSAGE_SECS, TimerMode::Once)),
+                Text::new(locale.text(TextKey::RallyBounces).replace("{}", &bounce_count.0.to_string())),
+                TextFont {
+                    font: asset_server.load(locale.font()),
+                    font_size: BOUNCE_MESSAGE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                TextLayout::new_with_justify(JustifyText::Center),
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(220.0),
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+            ));
+        }
+        bounce_count.0 = 0;
+    }
+}
+
+// 挡板击球加一，得分后清零
+fn count_rally(
+    mut rally: ResMut<Rally>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut score_events: EventReader<ScoreEvent>,
+) {
+    for event in collision_events.read() {
+        if matches!(event.kind, ColliderKind::Paddle(_)) {
+            rally.current += 1;
+            rally.best = rally.best.max(rally.current);
+        }
+    }
+    if score_events.read().count() > 0 {
+        rally.current = 0;
+    }
+}
+
+// 还没有击球时不显示
+fn apply_locale_font(
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+    mut text_query: Query<&mut TextFont, With<LocalizedFont>>,
+) {
+    if !locale.is_changed() {
+        return;
+    }
+    let font = asset_server.load(locale.font());
+    for mut text_font in &mut text_query {
+        text_font.font = font.clone();
+    }
+}
+
+fn update_rally_hud(rally: Res<Rally>, locale: Res<Locale>, mut hud: Query<&mut Text, With<RallyUi>>) {
+    if !rally.is_changed() && !locale.is_changed() {
+        return;
+    }
+    for mut text in &mut hud {
+        **text = match rally.current {
+            0 => String::new(),
+            current => format!("{} {current}", locale.text(TextKey::Rally)),
+        };
+    }
+}
+
+fn display_best_rally(
+    mut commands: Commands,
+    rally: Res<Rally>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    if rally.best == 0 {
+        return;
+    }
+    commands.spawn((
+        StateScoped(GameState::GameOver),
+        Text::new(format!("{} {}", locale.text(TextKey::BestRally), rally.best)),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: RALLY_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(60.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+fn expire_bounce_messages(
+    mut commands: Commands,
+    mut messages: Query<(Entity, &mut BounceMessage)>,
+    time: Res<Time>,
+) {
+    for (entity, mut message) in &mut messages {
+        if message.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn ball_reset(
+    mut commands: Commands,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), With<Ball>>,
+    split_balls: Query<Entity, With<SplitBall>>,
+    mut score_events: EventReader<ScoreEvent>,
+    mut serve: ResMut<Serve>,
+    mut serve_timer: ResMut<ServeTimer>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
+    next_state: Res<NextState<GameState>>,
+) {
+    // 决胜球不重新发球，让定格画面停在得分的一刻
+    if matches!(*next_state, NextState::Pending(GameState::WinFreeze)) {
+        score_events.clear();
+        return;
+    }
+    let Some(event) = score_events.read().last() else {
+        return;
+    };
+    let toward = event.serve_toward();
+    for entity in &split_balls {
+        commands.entity(entity).despawn();
+    }
+    let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+    *serve = serve_ball(toward, &mut ball_velocity, &mut ball_spin, &mut ball_transform, &mut rng, &arena);
+    serve_timer.0.reset();
+}
+
+// 从 toward 对面的半场发球，球朝 toward 一方飞去
+fn serve_ball(
+    toward: PaddleType,
+    ball_velocity: &mut Velocity,
+    ball_spin: &mut Spin,
+    ball_transform: &mut Transform,
+    rng: &mut GameRng,
+    arena: &Arena,
+) -> Serve {
+    ball_spin.0 = 0.0;
+
+    let sign  = if rng.random_bool(0.5) { 1.0 } else { -1.0 };
+    let temp_num = sign * rng.random_range(0.1..=0.5); // 随机发球角度
+    **ball_velocity = Vec2::new(toward.side(), temp_num).normalize() * BALL_SPEED; // 恢复球速
+
+    let position = serve_position(toward, arena);
+    ball_transform.translation.x = position.x;
+    ball_transform.translation.y = position.y;
+    Serve::new(ball_transform.translation, **ball_velocity)
+}
+
+// 发球点在发球方一侧的中线高度，离后墙 40，随场地宽度变化
+fn serve_position(toward: PaddleType, arena: &Arena) -> Vec2 {
+    match toward {
+        PaddleType::Right => Vec2::new(arena.left() + 40.0, 0.0),
+        PaddleType::Left => Vec2::new(arena.right() - 40.0, 0.0),
+    }
+}
+
+// F9 撤销上一分，只在非锦标赛模式下可用；决胜球后的定格期间撤销会取消这次胜利，
+// 进入结算画面后则无法再撤销
+fn undo_last_point(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    tournament: Res<TournamentMode>,
+    mut point_history: ResMut<PointHistory>,
+    mut progress: MatchProgress,
+    mut winner: ResMut<Winner>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), With<Ball>>,
+    mut serve: ResMut<Serve>,
+    mut serve_timer: ResMut<ServeTimer>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F9) || tournament.0 {
+        return;
+    }
+    let Some((scorer, before)) = point_history.0.pop() else {
+        return;
+    };
+    progress.restore(&before);
+
+    // 决胜球没有重新发球，恢复比赛时补上
+    if *state.get() == GameState::WinFreeze {
+        winner.0 = None;
+        next_state.set(GameState::Playing);
+        let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+        *serve = serve_ball(scorer.opponent(), &mut ball_velocity, &mut ball_spin, &mut ball_transform, &mut rng, &arena);
+        serve_timer.0.reset();
+    }
+}
+
+fn remember_pre_game_over(series: Res<Series>, bracket: Res<Bracket>, mut pre_game_over: ResMut<PreGameOver>) {
+    *pre_game_over = PreGameOver {
+        series: series.clone(),
+        bracket: bracket.clone(),
+    };
+}
+
+// 决胜时间为 0 时来不及在定格画面撤销，结算画面也可以按 F9：
+// 恢复系列赛和淘汰赛进度后重新进入比赛，由 resume_undone_match 还原得分前的状态。
+// 已经写入存档的最佳成绩和比赛录像不会撤回
+fn undo_final_point(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    tournament: Res<TournamentMode>,
+    mut point_history: ResMut<PointHistory>,
+    mut pre_game_over: ResMut<PreGameOver>,
+    mut series: ResMut<Series>,
+    mut bracket: ResMut<Bracket>,
+    mut resume: ResMut<ResumeMatch>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F9) || tournament.0 {
+        return;
+    }
+    let Some((scorer, before)) = point_history.0.pop() else {
+        return;
+    };
+    let pre_game_over = std::mem::take(&mut *pre_game_over);
+    *series = pre_game_over.series;
+    *bracket = pre_game_over.bracket;
+    resume.0 = Some(ResumedMatch {
+        scorer,
+        before,
+        history: std::mem::take(&mut point_history.0),
+    });
+    next_state.set(GameState::Playing);
+}
+
+fn resume_undone_match(
+    mut resume: ResMut<ResumeMatch>,
+    mut point_history: ResMut<PointHistory>,
+    mut progress: MatchProgress,
+    mut winner: ResMut<Winner>,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), With<Ball>>,
+    mut serve: ResMut<Serve>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
+) {
+    let Some(resumed) = resume.0.take() else {
+        return;
+    };
+    point_history.0 = resumed.history;
+    progress.restore(&resumed.before);
+    winner.0 = None;
+    let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+    *serve = serve_ball(resumed.scorer.opponent(), &mut ball_velocity, &mut ball_spin, &mut ball_transform, &mut rng, &arena);
+}
+
+// 比赛中按 End 直接回到主菜单，不计胜负
+fn quit_to_menu(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::End) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+// Paused 仍在 InMatch 内，恢复时不会触发 game_reset，球速和比分原样保留
+fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(match state.get() {
+            GameState::Paused => GameState::Playing,
+            _ => GameState::Paused,
+        });
+    }
+}
+
+// 半透明遮罩，记分板仍能透出来
+fn display_pause_overlay(mut commands: Commands, locale: Res<Locale>, asset_server: Res<AssetServer>) {
+    let pause_font = asset_server.load(locale.font());
+    let line = |key: TextKey, font_size: f32| {
+        (
+            Text::new(locale.text(key)),
+            TextFont {
+                font: pause_font.clone(),
+                font_size,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+        )
+    };
+
+    commands.spawn((
+        StateScoped(GameState::Paused),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        GlobalZIndex(1),
+        children![
+            line(TextKey::Paused, TITLE_FONT_SIZE),
+            line(TextKey::PressEscToResume, HINT_FONT_SIZE),
+        ],
+    ));
+}
+
+fn start_win_freeze(mut win_freeze: ResMut<WinFreeze>) {
+    win_freeze.0.reset();
+}
+
+// FixedUpdate 只在 Playing 中运行，定格期间球和挡板都不会动
+fn tick_win_freeze(
+    mut win_freeze: ResMut<WinFreeze>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if win_freeze.0.tick(time.delta()).finished() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+fn play_victory_jingle(mut commands: Commands, winner: Res<Winner>, jingle: Res<VictoryJingle>) {
+    if let Some(sound) = &jingle.custom {
+        commands.spawn((AudioPlayer(sound.clone()), PlaybackSettings::DESPAWN));
+        return;
+    }
+    let notes = if winner.0.is_some() { &jingle.win } else { &jingle.draw };
+    for note in notes {
+        commands.spawn((
+            AudioPlayer(note.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(0.2)),
+        ));
+    }
+}
+
+fn display_winner(
+    mut commands: Commands, 
+    winner: Res<Winner>,
+    game_mode: Res<GameMode>,
+    speedrun_clock: Res<SpeedrunClock>,
+    speedrun_record: Res<SpeedrunRecord>,
+    time_attack: Res<TimeAttack>,
+    mut menu_cursor: ResMut<MenuCursor>,
+    locale: Res<Locale>,
+    victory_messages: Res<VictoryMessages>,
+    player_names: Res<PlayerNames>,
+    key_bindings: Res<KeyBindings>,
+    swap_sides: Res<SwapSides>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let victory_font = asset_server.load(locale.font());
+    menu_cursor.0 = 0;
+
+    let message = match (*game_mode, winner.0) {
+        (GameMode::TimeAttack, Some(PaddleType::Left)) => locale.text(TextKey::TimeAttackClear).to_string(),
+        (GameMode::TimeAttack, _) => locale.text(TextKey::TimeUp).to_string(),
+        _ => victory_messages.message(winner.0, &player_names, *locale),
+    };
+
+    let speedrun_result = match *game_mode {
+        GameMode::Speedrun => speedrun_text(*locale, speedrun_clock.elapsed_secs(), speedrun_record.0),
+        GameMode::TimeAttack => speedrun_text(*locale, speedrun_clock.elapsed_secs(), time_attack.best),
+        _ => String::new(),
+    };
+    // 附上交换后的按键，提示双方换手
+    let swapped_rematch = format!(
+        "{} ({})",
+        locale.text(TextKey::RematchSwapped),
+        key_bindings.layout.label(!swap_sides.0)
+    );
+
+    // 文本背景框
+    commands.spawn((
+        StateScoped(GameState::GameOver),
+        Mesh2d(meshes.add(Rectangle::new(1000.0, 460.0))),
         MeshMaterial2d(materials.add(Color::BLACK)),
         Transform::from_translation(Vec3::new(0.0, -25.0, 0.0))
             .with_scale(Vec3::ONE),
         TextBackground,
     ));
 
-    // 胜利文本
+    // 胜利文本
+    commands.spawn((
+        StateScoped(GameState::GameOver),
+        VictoryText,
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.0),
+            right: Val::Px(0.0),
+            top: Val::Px(0.0),
+            bottom: Val::Px(0.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        children![
+            (
+                Text::new(message.to_string()),
+                TextFont {
+                    font: victory_font.clone(),
+                    font_size: VICTORY_TEXT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ),
+            (
+                Text::new(speedrun_result),
+                TextFont {
+                    font: victory_font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ),
+            (
+                Text::new(locale.text(TextKey::Rematch)),
+                MenuItem { index: 0, action: MenuAction::Rematch },
+                TextFont {
+                    font: victory_font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(MENU_SELECTED_COLOR),
+            ),
+            (
+                Text::new(swapped_rematch),
+                MenuItem { index: 1, action: MenuAction::RematchSwapped },
+                TextFont {
+                    font: victory_font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(MENU_UNSELECTED_COLOR),
+            ),
+            (
+                Text::new(locale.text(TextKey::MainMenu)),
+                MenuItem { index: 2, action: MenuAction::MainMenu },
+                TextFont {
+                    font: victory_font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(MENU_UNSELECTED_COLOR),
+            ),
+        ],
+    ));
+}
+
+fn game_over_keyboard(
+    mut next_state: ResMut<NextState<GameState>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    // K 键快速重开，等同于 REMATCH
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn navigate_menu(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut menu_cursor: ResMut<MenuCursor>,
+    mut items: Query<(&MenuItem, &mut TextColor)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut swap_sides: ResMut<SwapSides>,
+    mut player_names: ResMut<PlayerNames>,
+) {
+    let item_count = items.iter().count();
+    if item_count == 0 {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        menu_cursor.0 = (menu_cursor.0 + item_count - 1) % item_count;
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        menu_cursor.0 = (menu_cursor.0 + 1) % item_count;
+    }
+
+    for (item, mut color) in &mut items {
+        let selected = item.index == menu_cursor.0;
+        color.0 = if selected { MENU_SELECTED_COLOR } else { MENU_UNSELECTED_COLOR };
+
+        if selected && keyboard_input.just_pressed(KeyCode::Enter) {
+            match item.action {
+                MenuAction::Rematch => next_state.set(GameState::Playing),
+                // 名字跟着人走，人换到另一侧挡板
+                MenuAction::RematchSwapped => {
+                    swap_sides.0 = !swap_sides.0;
+                    let names = &mut *player_names;
+                    std::mem::swap(&mut names.left, &mut names.right);
+                    next_state.set(GameState::Playing);
+                }
+                MenuAction::MainMenu => next_state.set(GameState::MainMenu),
+            }
+        }
+    }
+}
+
+fn display_main_menu(
+    mut commands: Commands,
+    settings: SettingsView,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    let menu_font = asset_server.load(locale.font());
+
+    let menu = commands.spawn((
+        StateScoped(GameState::MainMenu),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        GlobalZIndex(1),
+        children![
+            (
+                Text::new(locale.text(TextKey::Title)),
+                TextFont {
+                    font: menu_font.clone(),
+                    font_size: TITLE_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ),
+            (
+                Text::new(locale.text(TextKey::PressSpaceToStart)),
+                TextFont {
+                    font: menu_font.clone(),
+                    font_size: HINT_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ),
+            (
+                Text::new(locale.text(TextKey::WatchLastMatch)),
+                TextFont {
+                    font: menu_font.clone(),
+                    font_size: SETTING_FONT_SIZE,
+                    ..default()
+                },
+                TextColor(MENU_UNSELECTED_COLOR),
+            ),
+        ],
+    )).id();
+
+    // 设置项太多，一列放不下，按两列排布；模式说明较长，独占一行
+    let settings_grid = commands.spawn((
+        ChildOf(menu),
+        Node {
+            display: Display::Grid,
+            grid_template_columns: RepeatedGridTrack::auto(2),
+            column_gap: Val::Px(60.0),
+            margin: UiRect::top(Val::Px(10.0)),
+            ..default()
+        },
+    )).id();
+    for label in SettingLabel::ALL {
+        let grid_column = match label {
+            SettingLabel::ModeDescription => GridPlacement::span(2),
+            _ => GridPlacement::DEFAULT,
+        };
+        commands.spawn((
+            ChildOf(settings_grid),
+            Node { grid_column, ..default() },
+            Text::new(label.text(&settings)),
+            label,
+            TextFont {
+                font: menu_font.clone(),
+                font_size: SETTING_FONT_SIZE,
+                ..default()
+            },
+            TextColor(MENU_UNSELECTED_COLOR),
+        ));
+    }
+}
+
+fn main_menu_keyboard(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut game_mode: ResMut<GameMode>,
+    mut theme: ResMut<Theme>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut accessibility: ResMut<Accessibility>,
+    mut display: ResMut<DisplaySettings>,
+    mut locale: ResMut<Locale>,
+    mut recording: ResMut<MatchRecording>,
+    mut match_replay: ResMut<MatchReplay>,
+    mut streak_bonus: ResMut<StreakBonus>,
+    mut swap_sides: ResMut<SwapSides>,
+    mut tournament: ResMut<TournamentMode>,
+    mut grace_period: ResMut<GracePeriod>,
+    mut center_spinner: ResMut<CenterSpinner>,
+    mut paddle_travel: ResMut<PaddleTravelSettings>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        next_state.set(GameState::Playing);
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyP) {
+        match MatchRecording::load() {
+            Some(loaded) => {
+                *game_mode = loaded.mode;
+                *recording = loaded;
+                match_replay.active = true;
+                next_state.set(GameState::Playing);
+            }
+            None => warn!("no match replay found in {MATCH_REPLAY_FILE}"),
+        }
+    }
+    if keyboard_input.any_just_pressed([KeyCode::KeyM, KeyCode::ArrowRight]) {
+        *game_mode = game_mode.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        *game_mode = game_mode.previous();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyL) {
+        theme.center_line = theme.center_line.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyC) {
+        theme.background = theme.background.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyK) {
+        *key_bindings = KeyBindings::from_layout(key_bindings.layout.next());
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        accessibility.reduce_motion = !accessibility.reduce_motion;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyV) {
+        display.present_mode = display.next_present_mode();
+        display.save();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        display.frame_cap = display.next_frame_cap();
+        display.save();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyZ) {
+        display.resolution = display.next_resolution();
+        display.save();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        *locale = locale.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyB) {
+        streak_bonus.enabled = !streak_bonus.enabled;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyX) {
+        swap_sides.0 = !swap_sides.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        tournament.0 = !tournament.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyW) {
+        grace_period.secs = grace_period.next_secs();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyS) {
+        center_spinner.speed = center_spinner.speed.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyH) {
+        let travel = paddle_travel.next_preset();
+        *paddle_travel = PaddleTravelSettings { left: travel, right: travel };
+    }
+}
+
+// 按 N 开始输入名字：TAB 切换左右，退格删除，回车或 ESC 完成并保存
+// 输入期间 main_menu_keyboard 不运行，字母键不会改动设置
+fn enter_player_names(
+    mut name_entry: ResMut<NameEntry>,
+    mut player_names: ResMut<PlayerNames>,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    let Some(side) = name_entry.0 else {
+        if keyboard_input.just_pressed(KeyCode::KeyN) {
+            name_entry.0 = Some(PaddleType::Left);
+            // 丢弃这一帧的按键，N 不计入名字
+            keyboard_events.clear();
+        }
+        return;
+    };
+
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        let name = player_names.get_mut(side);
+        match &event.logical_key {
+            Key::Enter | Key::Escape => {
+                name_entry.0 = None;
+                player_names.save();
+                return;
+            }
+            Key::Tab => {
+                name_entry.0 = Some(match side {
+                    PaddleType::Left => PaddleType::Right,
+                    PaddleType::Right => PaddleType::Left,
+                });
+                return;
+            }
+            Key::Backspace => {
+                name.pop();
+            }
+            Key::Space if !name.is_empty() && name.len() < MAX_NAME_LENGTH => name.push(' '),
+            // 字体只有大写 ASCII 字符
+            Key::Character(text) => {
+                for c in text.chars().filter(char::is_ascii_alphanumeric) {
+                    if name.len() < MAX_NAME_LENGTH {
+                        name.push(c.to_ascii_uppercase());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_player_name_labels(
+    player_names: Res<PlayerNames>,
+    mut labels: Query<(&PlayerNameUi, &mut Text)>,
+) {
+    if !player_names.is_changed() {
+        return;
+    }
+    for (label, mut text) in &mut labels {
+        **text = player_names.get(label.0).to_string();
+    }
+}
+
+// main_menu_keyboard 的参数已满，比赛规则相关的新选项放在这里
+fn match_options_keyboard(
+    mut roulette: ResMut<ModifierRoulette>,
+    mut difficulty: ResMut<Difficulty>,
+    mut endless: ResMut<EndlessMode>,
+    mut single_player: ResMut<SinglePlayer>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut bracket: ResMut<Bracket>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyO) {
+        roulette.toggle();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyD) {
+        *difficulty = difficulty.next();
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyE) {
+        endless.0 = !endless.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyJ) {
+        single_player.0 = !single_player.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        match_settings.target_score = match_settings.previous_target_score();
+    }
+    if keyboard_input.just_pressed(KeyCode::Equal) {
+        match_settings.target_score = match_settings.next_target_score();
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit5) {
+        match_settings.win_by_two = !match_settings.win_by_two;
+    }
+    if keyboard_input.just_pressed(KeyCode::Digit6) {
+        match_settings.serve_must_clear_net = !match_settings.serve_must_clear_net;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyY) {
+        bracket.size = bracket.next_size();
+    }
+}
+
+// Q / U 依次切换左、右挡板的输入来源
+fn controller_assignment_keyboard(
+    mut assignment: ResMut<ControllerAssignment>,
+    gamepads: Query<Entity, With<Gamepad>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    for (key, side) in [(KeyCode::KeyQ, PaddleType::Left), (KeyCode::KeyU, PaddleType::Right)] {
+        if keyboard_input.just_pressed(key) {
+            let source = assignment.get_mut(side);
+            *source = source.next(&sorted_gamepads(&gamepads));
+        }
+    }
+}
+
+// 新连接的手柄自动分给还在用键盘的一方，先左后右；已经手动分配过的不变
+fn assign_connected_gamepads(
+    mut assignment: ResMut<ControllerAssignment>,
+    connected: Query<Entity, Added<Gamepad>>,
+) {
+    let mut connected = connected.iter().collect::<Vec<_>>();
+    connected.sort();
+    for entity in connected {
+        let assigned = [PaddleType::Left, PaddleType::Right]
+            .into_iter()
+            .any(|side| assignment.get(side) == InputSource::Gamepad(entity));
+        if assigned {
+            continue;
+        }
+        if let Some(side) = [PaddleType::Left, PaddleType::Right]
+            .into_iter()
+            .find(|side| assignment.get(*side) == InputSource::Keyboard)
+        {
+            *assignment.get_mut(side) = InputSource::Gamepad(entity);
+        }
+    }
+}
+
+// 分配给挡板的手柄断开后改回键盘，并提示玩家
+fn fall_back_from_disconnected_gamepads(
+    mut commands: Commands,
+    mut assignment: ResMut<ControllerAssignment>,
+    gamepads: Query<(), With<Gamepad>>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    for side in [PaddleType::Left, PaddleType::Right] {
+        let InputSource::Gamepad(entity) = assignment.get(side) else {
+            continue;
+        };
+        if gamepads.contains(entity) {
+            continue;
+        }
+        *assignment.get_mut(side) = InputSource::Keyboard;
+        commands.spawn((
+            BounceMessage(Timer::from_seconds(GAMEPAD_WARNING_SECS, TimerMode::Once)),
+            Text::new(locale.text(TextKey::GamepadDisconnected)),
+            TextFont {
+                font: asset_server.load(locale.font()),
+                font_size: BOUNCE_MESSAGE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::srgb(1.0, 0.4, 0.4)),
+            TextLayout::new_with_justify(JustifyText::Center),
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(80.0),
+                width: Val::Percent(100.0),
+                ..default()
+            },
+        ));
+    }
+}
+
+// 数字键 1-4 依次切换上、下、左、右墙是否显示
+fn wall_visibility_keyboard(mut theme: ResMut<Theme>, keyboard_input: Res<ButtonInput<KeyCode>>) {
+    let keys = [
+        (KeyCode::Digit1, WallType::Top),
+        (KeyCode::Digit2, WallType::Bottom),
+        (KeyCode::Digit3, WallType::Left),
+        (KeyCode::Digit4, WallType::Right),
+    ];
+    for (key, wall_type) in keys {
+        if keyboard_input.just_pressed(key) {
+            let visible = theme.walls.get_mut(wall_type);
+            *visible = !*visible;
+        }
+    }
+}
+
+// I 键在几档暗角强度之间循环
+fn vignette_keyboard(mut theme: ResMut<Theme>, keyboard_input: Res<ButtonInput<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::KeyI) {
+        theme.vignette = theme.vignette.next();
+    }
+}
+
+// A 键切换场地宽高比；墙、背景和镜头都在 setup 中按场地尺寸生成，因此切换后重建整个场景
+fn arena_keyboard(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut arena: ResMut<Arena>,
+    root_entities: Query<Entity, (Or<(With<Transform>, With<Node>)>, Without<ChildOf>)>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyA) {
+        return;
+    }
+    arena.aspect = arena.next_aspect();
+    arena.save();
+    respawn_world(&mut commands, &root_entities);
+    commands.run_system_cached(display_main_menu);
+}
+
+// 清空场景中所有实体并重新执行 setup
+fn respawn_world(
+    commands: &mut Commands,
+    root_entities: &Query<Entity, (Or<(With<Transform>, With<Node>)>, Without<ChildOf>)>,
+) {
+    for entity in root_entities {
+        commands.entity(entity).despawn();
+    }
+    commands.run_system_cached(setup);
+}
+
+// 开启淘汰赛时每局开始前换上本场双方的名字；上一届已决出冠军时重新开始
+fn start_bracket_match(mut bracket: ResMut<Bracket>, mut player_names: ResMut<PlayerNames>) {
+    if bracket.size == 0 {
+        return;
+    }
+    if !bracket.in_progress() {
+        bracket.begin(&player_names);
+    }
+    player_names.left = bracket.players[bracket.entrants[0]].clone();
+    player_names.right = bracket.players[bracket.entrants[1]].clone();
+}
+
+// 平局时不晋级，REMATCH 重打同一场
+fn advance_bracket(mut bracket: ResMut<Bracket>, winner: Res<Winner>) {
+    if let (true, Some(winner)) = (bracket.in_progress(), winner.0) {
+        bracket.record(winner);
+    }
+}
+
+// 结算画面左侧列出本轮的赛果和接下来的对阵，决出冠军时只显示冠军
+fn display_bracket(
+    mut commands: Commands,
+    bracket: Res<Bracket>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    if bracket.size == 0 || bracket.players.is_empty() {
+        return;
+    }
+    let name = |index: usize| bracket.players[index].as_str();
+    let mut lines = Vec::new();
+    if let Some(champion) = bracket.champion {
+        lines.push(locale.text(TextKey::Champion).replace("{}", name(champion)));
+    } else {
+        lines.push(locale.text(TextKey::BracketRound).replace("{}", &bracket.round.to_string()));
+        for (won, lost) in &bracket.results {
+            lines.push(format!("{} {} {}", name(*won), locale.text(TextKey::Beat), name(*lost)));
+        }
+        for (index, pair) in bracket.entrants.chunks(2).enumerate() {
+            lines.push(match pair {
+                [left, right] if index == 0 => format!(
+                    "{}: {} {} {}",
+                    locale.text(TextKey::Next),
+                    name(*left),
+                    locale.text(TextKey::Versus),
+                    name(*right)
+                ),
+                [left, right] => format!("{} {} {}", name(*left), locale.text(TextKey::Versus), name(*right)),
+                [bye] => format!("{} {}", name(*bye), locale.text(TextKey::Bye)),
+                _ => continue,
+            });
+        }
+    }
+
+    let font = asset_server.load(locale.font());
+    commands
+        .spawn((
+            StateScoped(GameState::GameOver),
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(120.0),
+                left: Val::Px(20.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+        ))
+        .with_children(|column| {
+            for line in lines {
+                column.spawn((
+                    Text::new(line),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: SETTING_FONT_SIZE,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                ));
+            }
+        });
+}
+
+fn end_bracket(mut bracket: ResMut<Bracket>, mut player_names: ResMut<PlayerNames>) {
+    bracket.end(&mut player_names);
+}
+
+// 回到主菜单时把每局的比赛规则恢复默认并结束系列赛；REMATCH 不经过这里，因此会保留设置和连胜。
+// 显示、语言、场地、名字、主题、辅助功能等偏好，以及按键、手柄分配、换座位不在此列，
+// 淘汰赛由 end_bracket 结束
+fn reset_settings(
+    mut game_mode: ResMut<GameMode>,
+    mut ball_dynamics: ResMut<BallDynamics>,
+    mut series: ResMut<Series>,
+    mut difficulty: ResMut<Difficulty>,
+    mut match_settings: ResMut<MatchSettings>,
+    mut endless: ResMut<EndlessMode>,
+    mut single_player: ResMut<SinglePlayer>,
+    mut roulette: ResMut<ModifierRoulette>,
+    mut modifiers: ResMut<Modifiers>,
+    mut grace_period: ResMut<GracePeriod>,
+    mut center_spinner: ResMut<CenterSpinner>,
+    mut paddle_travel: ResMut<PaddleTravelSettings>,
+    mut streak_bonus: ResMut<StreakBonus>,
+    mut tournament: ResMut<TournamentMode>,
+) {
+    *game_mode = GameMode::default();
+    *ball_dynamics = BallDynamics::default();
+    *series = Series::default();
+    *difficulty = Difficulty::default();
+    *match_settings = MatchSettings::default();
+    *endless = EndlessMode::default();
+    *single_player = SinglePlayer::default();
+    *roulette = ModifierRoulette::default();
+    *modifiers = Modifiers::default();
+    *grace_period = GracePeriod::default();
+    *center_spinner = CenterSpinner::default();
+    *paddle_travel = PaddleTravelSettings::default();
+    *streak_bonus = StreakBonus::default();
+    *tournament = TournamentMode::default();
+}
+
+fn update_setting_labels(
+    settings: SettingsView,
+    mut labels: Query<(&SettingLabel, &mut Text)>,
+) {
+    for (label, mut text) in &mut labels {
+        let new_text = label.text(&settings);
+        if **text != new_text {
+            **text = new_text;
+        }
+    }
+}
+
+fn apply_background(theme: Res<Theme>, mut grid_lines: Query<&mut Visibility, With<BackgroundGrid>>) {
+    if !theme.is_changed() {
+        return;
+    }
+    for mut visibility in &mut grid_lines {
+        *visibility = match theme.background {
+            Background::Plain => Visibility::Hidden,
+            Background::Grid => Visibility::Inherited,
+        };
+    }
+}
+
+fn apply_vignette(theme: Res<Theme>, mut overlay: Query<(&mut Sprite, &mut Visibility), With<VignetteOverlay>>) {
+    if !theme.is_changed() {
+        return;
+    }
+    for (mut sprite, mut visibility) in &mut overlay {
+        sprite.color = Color::BLACK.with_alpha(theme.vignette.intensity());
+        *visibility = vignette_visibility(theme.vignette);
+    }
+}
+
+fn apply_center_line_color(
+    theme: Res<Theme>,
+    segments: Query<&MeshMaterial2d<ColorMaterial>, With<DashedLineSegment>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    for segment_material in &segments {
+        if let Some(material) = materials.get_mut(&segment_material.0) {
+            material.color = theme.center_line.color();
+        }
+    }
+}
+
+fn game_reset(
+    mut score: ResMut<Score>,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), (With<Ball>, Without<Paddle>)>,
+    mut paddle_query: Query<&mut Transform, (With<Paddle>, Without<Ball>)>,
+    mut modifiers: ResMut<Modifiers>,
+    mut roulette: ResMut<ModifierRoulette>,
+    mut serve: ResMut<Serve>,
+    mut serve_timer: ResMut<ServeTimer>,
+    game_mode: Res<GameMode>,
+    mut rng: ResMut<GameRng>,
+    mut recording: ResMut<MatchRecording>,
+    mut match_replay: ResMut<MatchReplay>,
+    streak_bonus: Res<StreakBonus>,
+    series: Res<Series>,
+    mut point_history: ResMut<PointHistory>,
+    arena: Res<Arena>,
+    resume: Res<ResumeMatch>,
+) {
+    // 重置分数   
+    score.0 = 0;
+    score.1 = 0;
+    point_history.0.clear();
+
+    // 重置挡板位置
+    for mut paddle_transform in paddle_query.iter_mut(){
+        paddle_transform.translation.y = 0.0;
+    }
+
+    begin_match(&mut rng, &mut recording, &mut match_replay, *game_mode, resume.0.is_some());
+
+    // 重置小球位置、速度；开局发球和得分后一样从本局的随机数里抽取角度，固定种子时发球序列相同
+    let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+    serve_ball(PaddleType::Right, &mut ball_velocity, &mut ball_spin, &mut ball_transform, &mut rng, &arena);
+    **ball_velocity *= streak_bonus.speed_multiplier(&series);
+    *serve = Serve::new(ball_transform.translation, **ball_velocity);
+    serve_timer.0.reset();
+
+    // 在播种之后抽取，回放同一局时会抽到同一个修正项
+    if let Some(modifier) = roulette.spin(&mut rng) {
+        modifier.apply(&mut modifiers);
+    }
+}
+
+// 紧接在 game_reset 之后运行，保证随机数的抽取顺序不变，回放时障碍位置一致
+fn spawn_match_obstacles(
+    commands: Commands,
+    modifiers: Res<Modifiers>,
+    mut rng: ResMut<GameRng>,
+    arena: Res<Arena>,
+    obstacle_layout: Res<ObstacleLayout>,
+) {
+    spawn_obstacles(commands, &modifiers, &mut rng, &arena, &obstacle_layout);
+}
+
+// 开局时短暂显示本局抽到的修正项
+fn announce_roulette_modifier(
+    mut commands: Commands,
+    roulette: Res<ModifierRoulette>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(modifier) = roulette.chosen else {
+        return;
+    };
+    commands.spawn((
+        StateScoped(InMatch),
+        BounceMessage(Timer::from_seconds(ROULETTE_MESSAGE_SECS, TimerMode::Once)),
+        Text::new(format!("{}: {}", locale.text(TextKey::Roulette), modifier.label(*locale))),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: BOUNCE_MESSAGE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(280.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+// 回放时沿用记录的种子，否则换一个新种子并清空输入记录；
+// 存档中设置了 game_seed 时每局都用这个种子，发球角度和障碍位置可以复现
+fn begin_match(
+    rng: &mut GameRng,
+    recording: &mut MatchRecording,
+    match_replay: &mut MatchReplay,
+    game_mode: GameMode,
+    resuming: bool,
+) {
+    if match_replay.active {
+        match_replay.cursor = 0;
+    } else if !resuming {
+        // 撤销决胜球后沿用原来的种子，障碍布局与撤销前相同
+        *recording = MatchRecording {
+            seed: load_saved("game_seed")
+                .and_then(|seed| seed.parse().ok())
+                .unwrap_or_else(|| rand::rng().random()),
+            mode: game_mode,
+            inputs: Vec::new(),
+            lets: Vec::new(),
+        };
+    }
+    rng.0 = StdRng::seed_from_u64(recording.seed);
+}
+
+// 比赛结束：正常比赛写入回放文件，回放则就此结束
+fn finish_match_recording(
+    recording: Res<MatchRecording>,
+    mut match_replay: ResMut<MatchReplay>,
+) {
+    if match_replay.active {
+        match_replay.active = false;
+    } else {
+        recording.save();
+    }
+}
+
+// 每局重新计数的规则状态
+fn reset_match_counters(
+    mut commands: Commands,
+    mut bounce_count: ResMut<BounceCount>,
+    mut replay_buffer: ResMut<ReplayBuffer>,
+    mut rally: ResMut<Rally>,
+    mut last_touch: ResMut<LastTouch>,
+    mut power_up_spawner: ResMut<PowerUpSpawner>,
+    mut lets: ResMut<Lets>,
+    held_balls: Query<Entity, With<HeldBall>>,
+) {
+    for entity in &held_balls {
+        commands.entity(entity).remove::<HeldBall>();
+    }
+    bounce_count.0 = 0;
+    replay_buffer.0.clear();
+    *rally = Rally::default();
+    last_touch.0 = None;
+    *power_up_spawner = PowerUpSpawner::default();
+    *lets = Lets::default();
+}
+
+fn apply_game_mode(
+    game_mode: Res<GameMode>,
+    difficulty: Res<Difficulty>,
+    endless: Res<EndlessMode>,
+    single_player: Res<SinglePlayer>,
+    match_settings: Res<MatchSettings>,
+    ball_dynamics: Res<BallDynamics>,
+    mut modifiers: ResMut<Modifiers>,
+    mut exit_behavior: ResMut<BallExitBehavior>,
+) {
+    *exit_behavior = match *game_mode {
+        GameMode::Chaos => BallExitBehavior::Despawn,
+        _ => BallExitBehavior::Reset,
+    };
+    // 各模式在共同的基础上只覆盖不同的项
+    let base = Modifiers {
+        obstacles: true,
+        obstacle_density: difficulty.obstacle_density(),
+        serve_must_clear_net: match_settings.serve_must_clear_net,
+        power_ups: true,
+        ball_split_chance: 0.0,
+        phase_chance: 0.0,
+        portals: false,
+        catch: false,
+        endless: endless.0,
+        target_score: match_settings.target_score,
+        win_by_two: match_settings.win_by_two,
+        ai_opponent: single_player.0,
+        elimination: false,
+        ball: ball_dynamics.clone(),
+    };
+    *modifiers = match *game_mode {
+        // 经典模式把修正项设为中性值，切回其他模式时恢复玩家配置
+        GameMode::Classic => Modifiers {
+            obstacles: false,
+            serve_must_clear_net: false,
+            power_ups: false,
+            ball: BallDynamics {
+                paddle_speed_up: 1.0,
+                momentum_transfer: None,
+                curve_strength: 0.0,
+                reflection_jitter: 0.0,
+                ..ball_dynamics.clone()
+            },
+            ..base
+        },
+        GameMode::Standard | GameMode::Speedrun => base,
+        GameMode::Chaos => Modifiers {
+            ball_split_chance: 0.25,
+            phase_chance: 0.5,
+            portals: true,
+            ..base
+        },
+        GameMode::Capture => Modifiers {
+            power_ups: false,
+            catch: true,
+            ..base
+        },
+        // 比赛的结束由限时挑战的计时决定，不走普通的目标分数
+        GameMode::TimeAttack => Modifiers {
+            power_ups: false,
+            endless: true,
+            ai_opponent: true,
+            ..base
+        },
+        GameMode::Elimination => Modifiers {
+            elimination: true,
+            ..base
+        },
+    };
+}
+
+fn spawn_obstacles(
+    mut commands: Commands,
+    modifiers: &Modifiers,
+    rng: &mut GameRng,
+    arena: &Arena,
+    layout: &ObstacleLayout,
+) {
+    if !modifiers.obstacles {
+        return;
+    }
+
+    // 生成障碍
+    let mut placed_obstacles: Vec<(Vec2, Vec2)> = Vec::new(); // 存储已放置的障碍(中心, 半尺寸)
+    let try_area = (
+        arena.left() + 100.0,
+        arena.right() - 100.0,
+        BOTTOM_WALL + 100.0,
+        TOP_WALL - 100.0,
+    );
+
+    // 固定布局：超出摆放区域（会挡住挡板或墙）的障碍跳过
+    if !layout.obstacles.is_empty() {
+        for (pos, size) in &layout.obstacles {
+            let half = *size / 2.0;
+            if pos.x - half.x < try_area.0
+                || pos.x + half.x > try_area.1
+                || pos.y - half.y < try_area.2
+                || pos.y + half.y > try_area.3
+            {
+                warn!("obstacle at {pos} does not fit in the arena, skipping");
+                continue;
+            }
+            let phase_chance = (placed_obstacles.is_empty() && modifiers.phase_chance > 0.0)
+                .then_some(modifiers.phase_chance);
+            placed_obstacles.push((*pos, half));
+            spawn_obstacle(&mut commands, *pos, *size, phase_chance, layout.durability);
+        }
+        return;
+    }
+
+    let density = modifiers.obstacle_density;
+    for _ in 0..density.count {
+        for _ in 0..50 { // 最多尝试50次
+            let mut size = Vec2::new(
+                rng.random_range(OBSTACLE_SIZE_RANGE[0].x..=OBSTACLE_SIZE_RANGE[0].y),
+                rng.random_range(OBSTACLE_SIZE_RANGE[1].x..=OBSTACLE_SIZE_RANGE[1].y),
+            ) * density.size_scale;
+            // 随机交换障碍的长和宽
+            if rng.random_bool(0.5) {
+                std::mem::swap(&mut size.x, &mut size.y);
+            }
+
+            let half = size / 2.0;
+            let pos = Vec2::new(
+                rng.random_range(try_area.0 + half.x..=try_area.1 - half.x),
+                rng.random_range(try_area.2 + half.y..=try_area.3 - half.y),
+            );
+
+            // 检查是否与已放置的障碍重叠
+            let mut overlap = false;
+            for (other_pos, other_half) in &placed_obstacles {
+                if (pos.x - other_pos.x).abs() < (half.x + other_half.x + 10.0) &&
+                   (pos.y - other_pos.y).abs() < (half.y + other_half.y + 10.0) {
+                    overlap = true;
+                    break;
+                }
+            }
+            // 检查是否与中间区域(计分板、地图中线)重叠
+            if pos.x.abs() < half.x + 150.0 {
+                overlap = true;
+            }
+
+            if !overlap {
+                // 第一个障碍做成幽灵障碍
+                let phase_chance = (placed_obstacles.is_empty() && modifiers.phase_chance > 0.0)
+                    .then_some(modifiers.phase_chance);
+                placed_obstacles.push((pos, half));
+                spawn_obstacle(&mut commands, pos, size, phase_chance, layout.durability);
+                break;
+            }
+        }
+    }
+}
+
+// 白色外框加黑色内块；幽灵障碍只保留外框碰撞体，内部黑块仅用于显示
+fn spawn_obstacle(
+    commands: &mut Commands,
+    pos: Vec2,
+    size: Vec2,
+    phase_chance: Option<f32>,
+    durability: Option<u32>,
+) {
+    let outline = commands
+        .spawn((
+            StateScoped(InMatch), // 开始新一局时重新生成，比赛结束自动销毁
+            Sprite::from_color(Color::WHITE, Vec2::ONE),
+            Transform {
+                translation: pos.extend(0.0),
+                scale: Vec3::new(size.x, size.y, 1.0),
+                ..default()
+            },
+            Obstacle,
+            Collider,
+            Restitution(OBSTACLE_RESTITUTION),
+            ObstacleOrigin { position: pos, size, phase_chance },
+        ))
+        .id();
+    if let Some(phase_chance) = phase_chance {
+        commands.entity(outline).insert(PhaseChance(phase_chance));
+    }
+    if let Some(durability) = durability {
+        commands.entity(outline).insert(Durability(durability));
+    }
+    let mut inner = commands.spawn((
+        StateScoped(InMatch),
+        Sprite::from_color(Color::BLACK, Vec2::ONE),
+        Transform {
+            translation: pos.extend(0.0),
+            scale: Vec3::new(size.x - 10.0, size.y - 10.0, 1.0),
+            ..default()
+        },
+    ));
+    if phase_chance.is_none() {
+        inner.insert((Obstacle, Collider));
+    }
+    let inner = inner.id();
+    commands.entity(outline).insert(ObstacleInner(inner));
+}
+
+// 可摧毁的障碍被主球撞到时扣耐久，耗尽时连同内部黑块一起清除；
+// 布局设置了重生时间时留下一个重生计时
+fn damage_obstacles(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut obstacles: Query<(&mut Durability, &ObstacleOrigin, &ObstacleInner)>,
+    layout: Res<ObstacleLayout>,
+) {
+    for event in collision_events.read() {
+        let Ok((mut durability, origin, inner)) = obstacles.get_mut(event.collider) else {
+            continue;
+        };
+        // 同一固定帧内多次接触时只摧毁一次
+        if durability.0 == 0 {
+            continue;
+        }
+        durability.0 -= 1;
+        if durability.0 > 0 {
+            continue;
+        }
+        commands.entity(event.collider).despawn();
+        commands.entity(inner.0).despawn();
+        if let Some(delay) = layout.respawn_delay {
+            commands.spawn((
+                StateScoped(InMatch),
+                *origin,
+                RespawnAfter(Timer::from_seconds(delay, TimerMode::Once)),
+            ));
+        }
+    }
+}
+
+// 重生计时结束后在原位置重新生成障碍；球正好在那里时等它离开，免得把球卡在障碍里
+fn respawn_obstacles(
+    mut commands: Commands,
+    mut pending: Query<(Entity, &ObstacleOrigin, &mut RespawnAfter)>,
+    balls: Query<&Transform, Or<(With<Ball>, With<ExtraBall>)>>,
+    layout: Res<ObstacleLayout>,
+    time: Res<Time>,
+) {
+    for (entity, origin, mut respawn) in &mut pending {
+        if !respawn.0.tick(time.delta()).finished() {
+            continue;
+        }
+        let area = Aabb2d::new(origin.position, origin.size / 2.0);
+        let occupied = balls
+            .iter()
+            .any(|transform| BoundingCircle::new(transform.translation.truncate(), BALL_SIZE / 2.).intersects(&area));
+        if occupied {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        spawn_obstacle(&mut commands, origin.position, origin.size, origin.phase_chance, layout.durability);
+    }
+}
+// 随机障碍不会放在中间区域，旋转障碍的臂长小于这块区域
+fn spawn_center_spinner(mut commands: Commands, mut center_spinner: ResMut<CenterSpinner>) {
+    center_spinner.angle = 0.0;
+    if center_spinner.speed.radians_per_sec().is_none() {
+        return;
+    }
+
+    // z 高于中线
+    for arm_angle in [0.0, std::f32::consts::FRAC_PI_2] {
+        commands.spawn((
+            StateScoped(InMatch),
+            SpinnerBar { arm_angle },
+            Sprite::from_color(Color::WHITE, Vec2::ONE),
+            Transform {
+                translation: Vec3::new(0.0, 0.0, 0.5),
+                rotation: Quat::from_rotation_z(arm_angle),
+                scale: Vec3::new(SPINNER_ARM_LENGTH * 2.0, SPINNER_THICKNESS, 1.0),
+            },
+            Obstacle,
+            Collider,
+        ));
+    }
+}
+
+fn spawn_portals(
+    mut commands: Commands,
+    modifiers: Res<Modifiers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !modifiers.portals {
+        return;
+    }
+    let ring = meshes.add(Annulus::new(PORTAL_RADIUS - 6.0, PORTAL_RADIUS));
+    let colors = [Color::srgb(0.2, 0.6, 1.0), Color::srgb(1.0, 0.55, 0.1)];
+    for (id, (position, color)) in PORTAL_POSITIONS.into_iter().zip(colors).enumerate() {
+        commands.spawn((
+            StateScoped(InMatch),
+            Portal {
+                id: id as u32,
+                partner: PORTAL_POSITIONS[1 - id],
+            },
+            Mesh2d(ring.clone()),
+            MeshMaterial2d(materials.add(color)),
+            Transform::from_translation(position.extend(0.5)),
+        ));
+    }
+}
+
+// 主球和额外的球都可以穿过传送门
+fn teleport_through_portals(
+    mut commands: Commands,
+    portal_query: Query<(&Portal, &Transform), Without<Velocity>>,
+    mut ball_query: Query<
+        (Entity, &mut Transform, Option<&mut PortalCooldown>),
+        (With<Velocity>, Or<(With<Ball>, With<ExtraBall>)>),
+    >,
+    time: Res<Time>,
+) {
+    for (ball_entity, mut ball_transform, cooldown) in &mut ball_query {
+        if cooldown.is_some_and(|mut cooldown| !cooldown.0.tick(time.delta()).finished()) {
+            continue;
+        }
+        let center = ball_transform.translation.truncate();
+        let entered = portal_query
+            .iter()
+            .find(|(_, portal_transform)| portal_transform.translation.truncate().distance(center) < PORTAL_RADIUS);
+        if let Some((portal, _)) = entered {
+            trace!("ball entered portal {}", portal.id);
+            ball_transform.translation.x = portal.partner.x;
+            ball_transform.translation.y = portal.partner.y;
+            commands
+                .entity(ball_entity)
+                .insert(PortalCooldown(Timer::from_seconds(PORTAL_COOLDOWN_SECS, TimerMode::Once)));
+        }
+    }
+}
+
+// 在固定帧中旋转，保证比赛回放时位置一致
+fn rotate_center_spinner(
+    mut center_spinner: ResMut<CenterSpinner>,
+    mut bars: Query<(&SpinnerBar, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let Some(speed) = center_spinner.speed.radians_per_sec() else {
+        return;
+    };
+    center_spinner.angle = (center_spinner.angle + speed * time.delta_secs()) % std::f32::consts::TAU;
+    for (bar, mut transform) in &mut bars {
+        transform.rotation = Quat::from_rotation_z(center_spinner.angle + bar.arm_angle);
+    }
+}
+
+// 发球即开始计时，限时挑战共用同一个计时器和 HUD
+fn start_speedrun(
+    mut commands: Commands,
+    game_mode: Res<GameMode>,
+    mut speedrun_clock: ResMut<SpeedrunClock>,
+    speedrun_record: Res<SpeedrunRecord>,
+    mut time_attack: ResMut<TimeAttack>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    speedrun_clock.reset();
+    time_attack.remaining = TIME_ATTACK_SECS;
+    let hud_text = match *game_mode {
+        GameMode::Speedrun => speedrun_text(*locale, 0.0, speedrun_record.0),
+        GameMode::TimeAttack => time_attack.text(*locale),
+        _ => return,
+    };
+
+    commands.spawn((
+        StateScoped(InMatch),
+        SpeedrunHud,
+        Text::new(hud_text),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: BOUNCE_MESSAGE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+fn start_grace_period(
+    mut commands: Commands,
+    mut grace_period: ResMut<GracePeriod>,
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+) {
+    grace_period.timer = Timer::from_seconds(grace_period.secs, TimerMode::Once);
+    if !grace_period.active() {
+        return;
+    }
+
+    commands.spawn((
+        StateScoped(InMatch),
+        WarmUpLabel,
+        Text::new(locale.text(TextKey::WarmUp)),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: BOUNCE_MESSAGE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(220.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+fn tick_grace_period(
+    mut commands: Commands,
+    mut grace_period: ResMut<GracePeriod>,
+    labels: Query<Entity, With<WarmUpLabel>>,
+    time: Res<Time>,
+) {
+    if grace_period.timer.tick(time.delta()).just_finished() {
+        for entity in &labels {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn tick_speedrun_clock(
+    game_mode: Res<GameMode>,
+    mut speedrun_clock: ResMut<SpeedrunClock>,
+    time: Res<Time>,
+) {
+    if matches!(*game_mode, GameMode::Speedrun | GameMode::TimeAttack) {
+        speedrun_clock.tick(time.delta());
+    }
+}
+
+// 玩家每得一分延长时间；拿到目标分数即成功，时间耗尽则算电脑获胜
+fn tick_time_attack(
+    game_mode: Res<GameMode>,
+    mut time_attack: ResMut<TimeAttack>,
+    mut score_events: EventReader<ScoreEvent>,
+    score: Res<Score>,
+    modifiers: Res<Modifiers>,
+    mut winner: ResMut<Winner>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if *game_mode != GameMode::TimeAttack {
+        score_events.clear();
+        return;
+    }
+    for event in score_events.read() {
+        if matches!(event, ScoreEvent::Player1Scored) {
+            time_attack.remaining += TIME_ATTACK_BONUS_SECS;
+        }
+    }
+    time_attack.remaining -= time.delta_secs();
+    if score.0 >= modifiers.target_score {
+        winner.0 = Some(PaddleType::Left);
+        next_state.set(GameState::WinFreeze);
+    } else if time_attack.remaining <= 0.0 {
+        winner.0 = Some(PaddleType::Right);
+        next_state.set(GameState::WinFreeze);
+    }
+}
+
+fn update_speedrun_hud(
+    game_mode: Res<GameMode>,
+    speedrun_clock: Res<SpeedrunClock>,
+    speedrun_record: Res<SpeedrunRecord>,
+    time_attack: Res<TimeAttack>,
+    locale: Res<Locale>,
+    mut hud: Query<&mut Text, With<SpeedrunHud>>,
+) {
+    for mut text in &mut hud {
+        **text = match *game_mode {
+            GameMode::TimeAttack => time_attack.text(*locale),
+            _ => speedrun_text(*locale, speedrun_clock.elapsed_secs(), speedrun_record.0),
+        };
+    }
+}
+
+fn speedrun_text(locale: Locale, elapsed: f32, best: Option<f32>) -> String {
+    let time = locale.text(TextKey::Time);
+    match best {
+        Some(best) => format!("{time} {elapsed:.2}  {} {best:.2}", locale.text(TextKey::Best)),
+        None => format!("{time} {elapsed:.2}"),
+    }
+}
+
+// 比赛结束时停止计时，刷新并保存最佳用时
+fn finish_speedrun(
+    game_mode: Res<GameMode>,
+    winner: Res<Winner>,
+    mut speedrun_clock: ResMut<SpeedrunClock>,
+    mut speedrun_record: ResMut<SpeedrunRecord>,
+) {
+    speedrun_clock.pause();
+    if *game_mode != GameMode::Speedrun || winner.0.is_none() {
+        return;
+    }
+
+    let elapsed = speedrun_clock.elapsed_secs();
+    if speedrun_record.0.is_none_or(|best| elapsed < best) {
+        speedrun_record.0 = Some(elapsed);
+        store_saved("speedrun_best", &elapsed.to_string());
+    }
+}
+
+// 通关时刷新并保存最快用时
+fn finish_time_attack(
+    game_mode: Res<GameMode>,
+    winner: Res<Winner>,
+    speedrun_clock: Res<SpeedrunClock>,
+    mut time_attack: ResMut<TimeAttack>,
+) {
+    if *game_mode != GameMode::TimeAttack || winner.0 != Some(PaddleType::Left) {
+        return;
+    }
+    let elapsed = speedrun_clock.elapsed_secs();
+    if time_attack.best.is_none_or(|best| elapsed < best) {
+        time_attack.best = Some(elapsed);
+        store_saved("time_attack_best", &elapsed.to_string());
+    }
+}
+
+fn load_saved(key: &str) -> Option<String> {
+    let contents = std::fs::read_to_string(SAVE_FILE).ok()?;
+    contents.lines().find_map(|line| {
+        let (saved_key, value) = line.split_once('=')?;
+        (saved_key == key).then(|| value.to_string())
+    })
+}
+
+fn store_saved(key: &str, value: &str) {
+    let contents = std::fs::read_to_string(SAVE_FILE).unwrap_or_default();
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter(|line| line.split_once('=').is_none_or(|(saved_key, _)| saved_key != key))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{key}={value}"));
+    if let Err(err) = std::fs::write(SAVE_FILE, lines.join("\n")) {
+        warn!("failed to write {SAVE_FILE}: {err}");
+    }
+}
+
+fn record_replay_frame(
+    mut replay_buffer: ResMut<ReplayBuffer>,
+    ball_query: Single<&Transform, With<Ball>>,
+    paddle_query: Query<(&Transform, &PaddleType), With<Paddle>>,
+    time: Res<Time>,
+) {
+    let mut frame = ReplayFrame {
+        ball: ball_query.translation,
+        left_paddle: Vec3::ZERO,
+        right_paddle: Vec3::ZERO,
+    };
+    for (paddle_transform, paddle_type) in &paddle_query {
+        match paddle_type {
+            PaddleType::Left => frame.left_paddle = paddle_transform.translation,
+            PaddleType::Right => frame.right_paddle = paddle_transform.translation,
+        }
+    }
+
+    let capacity = (REPLAY_SECONDS / time.delta_secs()) as usize;
+    replay_buffer.0.push_back(frame);
+    while replay_buffer.0.len() > capacity {
+        replay_buffer.0.pop_front();
+    }
+}
+
+fn start_instant_replay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    replay_buffer: Res<ReplayBuffer>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyR) && !replay_buffer.0.is_empty() {
+        next_state.set(GameState::InstantReplay);
+    }
+}
+
+// 得分后（比赛未结束时）自动播放进球回放
+fn start_goal_replay(
+    mut score_events: EventReader<ScoreEvent>,
+    accessibility: Res<Accessibility>,
+    replay_buffer: Res<ReplayBuffer>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if score_events.is_empty() {
+        return;
+    }
+    score_events.clear();
+    let game_over = matches!(*next_state, NextState::Pending(GameState::WinFreeze));
+    if game_over || accessibility.reduce_motion || replay_buffer.0.is_empty() {
+        return;
+    }
+    playback.goal_camera = true;
+    next_state.set(GameState::InstantReplay);
+}
+
+// 隐藏实际的球和挡板，用半透明的替身播放回放
+fn spawn_replay_ghosts(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    replay_buffer: Res<ReplayBuffer>,
+    mut live_query: Query<&mut Visibility, Or<(With<Ball>, With<Paddle>)>>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    playback.cursor = if playback.goal_camera {
+        let frames = GOAL_REPLAY_SECONDS / fixed_time.timestep().as_secs_f32();
+        (replay_buffer.0.len() as f32 - frames).max(0.0)
+    } else {
+        0.0
+    };
+    for mut visibility in &mut live_query {
+        *visibility = Visibility::Hidden;
+    }
+
+    let ghost_color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+    let ghosts = [
+        (ReplayGhost::Ball, Vec2::splat(BALL_SIZE)),
+        (ReplayGhost::Paddle(PaddleType::Left), PADDLE_SIZE),
+        (ReplayGhost::Paddle(PaddleType::Right), PADDLE_SIZE),
+    ];
+    for (ghost, size) in ghosts {
+        commands.spawn((
+            StateScoped(GameState::InstantReplay),
+            Sprite::from_color(ghost_color, Vec2::ONE),
+            Transform::from_scale(size.extend(1.0)),
+            ghost,
+        ));
+    }
+
+    commands.spawn((
+        StateScoped(GameState::InstantReplay),
+        Text::new(locale.text(TextKey::Replay)),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: BOUNCE_MESSAGE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(40.0),
+            width: Val::Percent(100.0),
+            ..default()
+        },
+    ));
+}
+
+fn play_instant_replay(
+    mut playback: ResMut<ReplayPlayback>,
+    replay_buffer: Res<ReplayBuffer>,
+    mut ghosts: Query<(&mut Transform, &ReplayGhost), Without<Camera2d>>,
+    mut camera: Single<&mut Transform, With<Camera2d>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    fixed_time: Res<Time<Fixed>>,
+) {
+    let skipped = keyboard_input.just_pressed(KeyCode::Space);
+    let Some(frame) = replay_buffer.0.get(playback.cursor as usize).filter(|_| !skipped) else {
+        // 播放完毕或按空格跳过，从暂停处继续比赛
+        next_state.set(GameState::Playing);
+        return;
+    };
+
+    if playback.goal_camera {
+        camera.translation = frame.ball.truncate().extend(camera.translation.z);
+        camera.scale = Vec3::splat(GOAL_CAMERA_ZOOM);
+    }
+
+    for (mut ghost_transform, ghost) in &mut ghosts {
+        ghost_transform.translation = match ghost {
+            ReplayGhost::Ball => frame.ball,
+            ReplayGhost::Paddle(PaddleType::Left) => frame.left_paddle,
+            ReplayGhost::Paddle(PaddleType::Right) => frame.right_paddle,
+        };
+    }
+
+    playback.cursor += time.delta_secs() / fixed_time.timestep().as_secs_f32() * REPLAY_PLAYBACK_SPEED;
+}
+
+fn show_live_entities(
+    mut live_query: Query<&mut Visibility, Or<(With<Ball>, With<Paddle>)>>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut camera: Single<&mut Transform, With<Camera2d>>,
+) {
+    for mut visibility in &mut live_query {
+        *visibility = Visibility::Inherited;
+    }
+    // 镜头切回全场
+    playback.goal_camera = false;
+    **camera = Transform::default();
+}
+
+// 减少动态效果时不生成印记
+fn spawn_wall_marks(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    accessibility: Res<Accessibility>,
+) {
+    for event in collision_events.read() {
+        let ColliderKind::Wall(wall_type @ (WallType::Top | WallType::Bottom)) = event.kind else {
+            continue;
+        };
+        if accessibility.reduce_motion {
+            continue;
+        }
+        // 印记嵌在墙体表面以内
+        let inward = if wall_type == WallType::Top { 1.0 } else { -1.0 };
+        commands.spawn((
+            StateScoped(InMatch),
+            WallMark {
+                timer: Timer::from_seconds(WALL_MARK_SECS, TimerMode::Once),
+            },
+            Sprite::from_color(Color::srgb(0.3, 0.3, 0.3), Vec2::ONE),
+            Transform {
+                translation: Vec3::new(event.position.x, event.position.y + inward * 4.0, 0.5),
+                scale: Vec3::new(BALL_SIZE, 6.0, 1.0),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn fade_wall_marks(
+    mut commands: Commands,
+    mut marks: Query<(Entity, &mut WallMark, &mut Sprite)>,
+    time: Res<Time>,
+) {
+    for (entity, mut mark, mut sprite) in &mut marks {
+        mark.timer.tick(time.delta());
+        if mark.timer.finished() {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(mark.timer.fraction_remaining());
+        }
+    }
+}
+
+// 界面按相同比例缩放，保持与场地对齐
+fn apply_resolution(display: Res<DisplaySettings>, mut window: Single<&mut Window>) {
+    if !display.is_changed() {
+        return;
+    }
+    let resolution = display.resolution.as_vec2();
+    if window.resolution.size() != resolution {
+        window.resolution.set(resolution.x, resolution.y);
+    }
+}
+
+// 按窗口实际的逻辑高度缩放界面。高 DPI 屏幕上系统可能缩小窗口或改变缩放系数，
+// 实际尺寸不一定等于选择的分辨率
+fn apply_ui_scale(window: Single<Ref<Window>>, mut ui_scale: ResMut<UiScale>) {
+    if !window.is_changed() || window.height() <= 0.0 {
+        return;
+    }
+    let scale = window.height() / DESIGN_HEIGHT;
+    if ui_scale.0 != scale {
+        ui_scale.0 = scale;
+    }
+}
+
+fn apply_present_mode(display: Res<DisplaySettings>, mut window: Single<&mut Window>) {
+    if display.is_changed() && window.present_mode != display.present_mode {
+        window.present_mode = display.present_mode;
+    }
+}
+
+// 在帧末尾休眠补足剩余时间，与 VSync 设置互不影响
+fn limit_frame_rate(display: Res<DisplaySettings>, mut last_frame: Local<Option<Instant>>) {
+    if let (Some(cap), Some(last_frame)) = (display.frame_cap, *last_frame) {
+        let frame_time = Duration::from_secs_f64(1.0 / f64::from(cap));
+        let elapsed = last_frame.elapsed();
+        if elapsed < frame_time {
+            std::thread::sleep(frame_time - elapsed);
+        }
+    }
+    *last_frame = Some(Instant::now());
+}
+
+fn trigger_paddle_recoil(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    paddle_query: Query<(Entity, &PaddleType), With<Paddle>>,
+) {
+    for event in collision_events.read() {
+        let ColliderKind::Paddle(hit_paddle) = event.kind else {
+            continue;
+        };
+        // 只有正面击球才会把挡板往后推，擦到挡板上下端时不后坐
+        if event.speed < HARD_HIT_SPEED || !matches!(event.side, Collision::Left | Collision::Right) {
+            continue;
+        }
+        let amount = (MAX_PADDLE_RECOIL * event.speed / (HARD_HIT_SPEED * 2.0)).min(MAX_PADDLE_RECOIL);
+        for (entity, paddle_type) in &paddle_query {
+            if *paddle_type == hit_paddle {
+                commands.entity(entity).insert(PaddleRecoil {
+                    timer: Timer::from_seconds(PADDLE_RECOIL_SECS, TimerMode::Once),
+                    amount,
+                });
+            }
+        }
+    }
+}
+
+// 挡板朝自己一侧的墙后退，随后缓回固定列
+fn animate_paddle_recoil(
+    mut commands: Commands,
+    mut paddle_query: Query<(Entity, &mut Transform, &PaddleType, &mut PaddleRecoil)>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+) {
+    for (entity, mut paddle_transform, paddle_type, mut recoil) in &mut paddle_query {
+        recoil.timer.tick(time.delta());
+        let remaining = recoil.timer.fraction_remaining();
+        let offset = recoil.amount * remaining * remaining;
+        paddle_transform.translation.x = paddle_type.column_x(&arena) + paddle_type.side() * offset;
+        if recoil.timer.finished() {
+            commands.entity(entity).remove::<PaddleRecoil>();
+        }
+    }
+}
+
+// 上下墙随回合强度（球速）变亮，得分后球速恢复时逐渐暗下去
+fn update_wall_glow(
+    ball_query: Single<&Velocity, With<Ball>>,
+    modifiers: Res<Modifiers>,
+    accessibility: Res<Accessibility>,
+    theme: Res<Theme>,
+    mut walls: Query<(&WallType, &mut Sprite), With<Wall>>,
+    mut glow: Local<f32>,
+    time: Res<Time>,
+) {
+    let target = if accessibility.reduce_motion {
+        0.0
+    } else {
+        ((ball_query.length() - BALL_SPEED) / (modifiers.ball.max_speed - BALL_SPEED)).clamp(0.0, 1.0)
+    };
+    *glow += (target - *glow) * (1.0 - (-WALL_GLOW_RESPONSE * time.delta_secs()).exp());
+
+    // 同时按主题设置决定每面墙是否显示
+    for (wall_type, mut sprite) in &mut walls {
+        sprite.color = if !theme.walls.is_visible(*wall_type) {
+            Color::NONE
+        } else if matches!(wall_type, WallType::Top | WallType::Bottom) {
+            WALL_COLOR.mix(&WALL_GLOW_COLOR, *glow)
+        } else {
+            SIDE_WALL_COLOR
+        };
+    }
+}
+
+fn update_series(winner: Res<Winner>, mut series: ResMut<Series>) {
+    let Some(winner) = winner.0 else {
+        return;
+    };
+    if series.last_winner == Some(winner) {
+        series.streak += 1;
+    } else {
+        *series = Series {
+            last_winner: Some(winner),
+            streak: 1,
+        };
+    }
+}
+
+// 盖住整个窗口，场景在 Startup 中已经生成，淡出后直接露出主菜单
+fn display_splash(mut commands: Commands, locale: Res<Locale>, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        StateScoped(GameState::Splash),
+        SplashUi,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        GlobalZIndex(2),
+        children![(
+            SplashUi,
+            Text::new(locale.text(TextKey::Title)),
+            TextFont {
+                font: asset_server.load(locale.font()),
+                font_size: TITLE_FONT_SIZE,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+        )],
+    ));
+}
+
+// 计时结束后进入主菜单，按空格可跳过；存档中没有 tutorial_seen 时视为首次启动，先显示操作说明
+fn tick_splash(
+    mut splash_timer: ResMut<SplashTimer>,
+    mut splash_ui: Query<(Option<&mut BackgroundColor>, Option<&mut TextColor>), With<SplashUi>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    let timer = &mut splash_timer.0;
+    timer.tick(time.delta());
+    let alpha = (timer.remaining_secs() / SPLASH_FADE_SECS).min(1.0);
+    for (background, text_color) in &mut splash_ui {
+        if let Some(mut background) = background {
+            background.0.set_alpha(alpha);
+        }
+        if let Some(mut text_color) = text_color {
+            text_color.0.set_alpha(alpha);
+        }
+    }
+    if timer.finished() || keyboard_input.just_pressed(KeyCode::Space) {
+        next_state.set(if load_saved("tutorial_seen").is_none() {
+            GameState::Tutorial
+        } else {
+            GameState::MainMenu
+        });
+    }
+}
+
+fn display_tutorial(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    match_settings: Res<MatchSettings>,
+    asset_server: Res<AssetServer>,
+) {
+    let tutorial_font = asset_server.load(locale.font());
+    let line = |text: String, font_size: f32| {
+        (
+            Text::new(text),
+            TextFont {
+                font: tutorial_font.clone(),
+                font_size,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                margin: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+        )
+    };
+
+    commands.spawn((
+        StateScoped(GameState::Tutorial),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+        GlobalZIndex(1),
+        children![
+            line(locale.text(TextKey::TutorialTitle).to_string(), TITLE_FONT_SIZE),
+            line(locale.text(TextKey::TutorialControls).to_string(), SETTING_FONT_SIZE),
+            line(
+                locale.text(TextKey::TutorialScoring).replace("{}", &match_settings.target_score.to_string()),
+                SETTING_FONT_SIZE,
+            ),
+            line(locale.text(TextKey::TutorialSpeedUp).to_string(), SETTING_FONT_SIZE),
+            line(locale.text(TextKey::TutorialDismiss).to_string(), HINT_FONT_SIZE),
+        ],
+    ));
+}
+
+fn dismiss_tutorial(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        store_saved("tutorial_seen", "true");
+        next_state.set(GameState::Playing);
+    }
+}
+
+// F8 开关压力测试
+fn toggle_stress_test(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut stress_test: ResMut<StressTest>,
+    stress_entities: Query<Entity, With<StressTestEntity>>,
+    asset_server: Res<AssetServer>,
+    arena: Res<Arena>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F8) {
+        return;
+    }
+    stress_test.active = !stress_test.active;
+    if !stress_test.active {
+        for entity in &stress_entities {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+
+    // 只用于调试，不使用比赛的 GameRng，以免影响比赛回放
+    let mut rng = rand::rng();
+    for _ in 0..stress_test.obstacle_count {
+        let pos = Vec2::new(
+            rng.random_range(arena.left() + 100.0..=arena.right() - 100.0),
+            rng.random_range(BOTTOM_WALL + 50.0..=TOP_WALL - 50.0),
+        );
+        commands.spawn((
+            StateScoped(InMatch),
+            StressTestEntity,
+            Sprite::from_color(Color::srgb(0.3, 0.3, 0.3), Vec2::ONE),
+            Transform {
+                translation: pos.extend(0.0),
+                scale: Vec3::new(16.0, 16.0, 1.0),
+                ..default()
+            },
+            Obstacle,
+            Collider,
+        ));
+    }
+    for _ in 0..stress_test.ball_count {
+        let pos = Vec2::new(
+            rng.random_range(arena.left() + 100.0..=arena.right() - 100.0),
+            rng.random_range(BOTTOM_WALL + 50.0..=TOP_WALL - 50.0),
+        );
+        let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        commands.spawn((
+            StateScoped(InMatch),
+            StressTestEntity,
+            ExtraBall,
+            Sprite::from_color(Color::srgba(1.0, 1.0, 1.0, 0.5), Vec2::ONE),
+            Transform {
+                translation: pos.extend(1.0),
+                scale: Vec3::new(BALL_SIZE, BALL_SIZE, 1.0),
+                ..default()
+            },
+            Velocity(direction * BALL_SPEED),
+        ));
+    }
+
+    commands.spawn((
+        StateScoped(InMatch),
+        StressTestEntity,
+        StressTestHud,
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/Bit3.ttf"),
+            font_size: SETTING_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+    ));
+}
+
+// 压力测试的实体随比赛一起销毁，这里只需复位开关
+fn stop_stress_test(mut stress_test: ResMut<StressTest>) {
+    stress_test.active = false;
+}
+
+// 平均帧时间用指数滑动平均，数字不会每帧乱跳
+fn update_stress_test_hud(
+    mut stress_test: ResMut<StressTest>,
+    mut hud: Query<&mut Text, With<StressTestHud>>,
+    time: Res<Time>,
+) {
+    if !stress_test.active {
+        return;
+    }
+    stress_test.average_frame_secs = stress_test.average_frame_secs * 0.95 + time.delta_secs() * 0.05;
+    for mut text in &mut hud {
+        **text = format!(
+            "STRESS TEST  {} BALLS  {} OBSTACLES  {:.2} MS",
+            stress_test.ball_count,
+            stress_test.obstacle_count,
+            stress_test.average_frame_secs * 1000.0
+        );
+    }
+}
+
+fn rebuild_collider_grid(
+    mut collider_grid: ResMut<ColliderGrid>,
+    collider_query: Query<(Entity, &Transform), With<Collider>>,
+) {
+    collider_grid.rebuild(
+        collider_query
+            .iter()
+            .map(|(entity, transform)| (entity, collider_bounds(transform))),
+    );
+}
+
+fn spawn_split_ball(commands: &mut Commands, position: Vec3, velocity: Vec2) {
+    commands.spawn((
+        StateScoped(InMatch),
+        SplitBall,
+        ExtraBall,
+        Sprite::from_color(Color::srgb(0.8, 0.8, 1.0), Vec2::ONE),
+        Transform {
+            translation: position,
+            scale: Vec3::new(BALL_SIZE, BALL_SIZE, 1.0),
+            ..default()
+        },
+        Velocity(velocity),
+    ));
+}
+
+// 额外的球只与网格中附近的碰撞体检测，避免 O(球数 x 碰撞体数)
+fn check_extra_ball_collisions(
+    mut commands: Commands,
+    mut ball_query: Query<(Entity, &mut Velocity, &mut Transform, Has<SplitBall>), With<ExtraBall>>,
+    collider_grid: Res<ColliderGrid>,
+    collider_query: Query<(&Transform, Option<&Restitution>, Option<&WallType>), (With<Collider>, Without<ExtraBall>)>,
+    modifiers: Res<Modifiers>,
+    exit_behavior: Res<BallExitBehavior>,
+    mut score: ResMut<Score>,
+    mut health: ResMut<Health>,
+    mut winner: ResMut<Winner>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut score_events: EventWriter<ScoreEvent>,
+) {
+    for (ball_entity, mut ball_velocity, mut ball_transform, is_split_ball) in &mut ball_query {
+        let center = ball_transform.translation.truncate();
+        let ball = BoundingCircle::new(center, BALL_SIZE / 2.);
+        for entity in collider_grid.nearby(Aabb2d::new(center, Vec2::splat(BALL_SIZE / 2.))) {
+            let Ok((collider_transform, maybe_restitution, maybe_wall_type)) = collider_query.get(entity) else {
+                continue;
+            };
+            if let Some((collision, _)) = collider_collision(ball, collider_transform) {
+                let scorer = match maybe_wall_type {
+                    Some(WallType::Right) => Some(PaddleType::Left),
+                    Some(WallType::Left) => Some(PaddleType::Right),
+                    _ => None,
+                };
+                if let (true, Some(scorer)) = (is_split_ball, scorer) {
+                    match *exit_behavior {
+                        BallExitBehavior::Reset => {
+                            ball_transform.translation.x = 0.0;
+                            ball_transform.translation.y = 0.0;
+                            ball_velocity.x = -ball_velocity.x;
+                        }
+                        // 得分结束本回合，ball_reset 会清除其余的分裂球
+                        BallExitBehavior::Despawn => {
+                            commands.entity(ball_entity).despawn();
+                            award_point(scorer, &modifiers, &mut score, &mut health, &mut winner, &mut next_state, &mut score_events);
+                            return;
+                        }
+                    }
+                    break;
+                }
+                let restitution = maybe_restitution.map_or(1.0, |restitution| restitution.0);
+                reflect_velocity(&mut ball_velocity, collision, restitution);
+                **ball_velocity = ball_velocity.clamp_length(modifiers.ball.min_speed, modifiers.ball.max_speed);
+            }
+        }
+    }
+}
+
+// 压力测试时两侧挡板都跟着主球上下移动
+fn stress_test_autopilot(
+    stress_test: Res<StressTest>,
+    ball_query: Single<&Transform, With<Ball>>,
+    paddle_query: Query<(&Transform, &PaddleType), With<Paddle>>,
+    mut paddle_input: ResMut<PaddleInput>,
+) {
+    if !stress_test.active {
+        return;
+    }
+    for (paddle_transform, paddle_type) in &paddle_query {
+        let offset = ball_query.translation.y - paddle_transform.translation.y;
+        let command = PaddleCommand {
+            direction: if offset.abs() < PADDLE_SIZE.y / 4.0 { 0.0 } else { offset.signum() },
+            boost: true,
+            block: false,
+            target: None,
+        };
+        match paddle_type {
+            PaddleType::Left => paddle_input.left = command,
+            PaddleType::Right => paddle_input.right = command,
+        }
+    }
+}
+
+// 电脑控制的右挡板：球朝自己飞来时追球，否则回到中间等待。
+// 和玩家一样经过 PaddleInput 和 move_paddle，靠近目标时减速，输入的变化速度由难度决定
+// 按本局设置给右侧挡板挂上或摘掉 AiControlled
+fn assign_ai_paddle(
+    mut commands: Commands,
+    modifiers: Res<Modifiers>,
+    paddle_query: Query<(Entity, &PaddleType), With<Paddle>>,
+) {
+    for (entity, paddle_type) in &paddle_query {
+        if modifiers.ai_opponent && *paddle_type == PaddleType::Right {
+            commands.entity(entity).insert(AiControlled);
+        } else {
+            commands.entity(entity).remove::<AiControlled>();
+        }
+    }
+}
+
+fn move_ai_paddle(
+    difficulty: Res<Difficulty>,
+    ball_query: Single<(&Transform, &Velocity), With<Ball>>,
+    paddle_query: Query<&Transform, (With<Paddle>, With<AiControlled>)>,
+    mut paddle_input: ResMut<PaddleInput>,
+    mut direction: Local<f32>,
+    time: Res<Time>,
+) {
+    if paddle_query.is_empty() {
+        *direction = 0.0;
+        return;
+    }
+    let (ball_transform, ball_velocity) = *ball_query;
+    for paddle_transform in &paddle_query {
+        let target = if ball_velocity.x > 0.0 { ball_transform.translation.y } else { 0.0 };
+        let offset = target - paddle_transform.translation.y;
+        let desired = if offset.abs() < AI_DEADZONE {
+            0.0
+        } else {
+            (offset / AI_EASE_DISTANCE).clamp(-1.0, 1.0)
+        };
+        let max_change = difficulty.ai_response() * time.delta_secs();
+        *direction += (desired - *direction).clamp(-max_change, max_change);
+        paddle_input.right = PaddleCommand {
+            direction: *direction,
+            boost: false,
+            block: false,
+            target: None,
+        };
+    }
+}
+
+fn track_last_touch(
+    mut last_touch: ResMut<LastTouch>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut score_events: EventReader<ScoreEvent>,
+) {
+    for event in collision_events.read() {
+        if let ColliderKind::Paddle(paddle) = event.kind {
+            last_touch.0 = Some(paddle);
+        }
+    }
+    if score_events.read().count() > 0 {
+        last_touch.0 = None;
+    }
+}
+
+// 场上同时最多一个道具；使用 GameRng，比赛回放时位置一致
+fn spawn_power_ups(
+    mut commands: Commands,
+    mut spawner: ResMut<PowerUpSpawner>,
+    mut rng: ResMut<GameRng>,
+    modifiers: Res<Modifiers>,
+    power_ups: Query<(), With<PowerUp>>,
+    arena: Res<Arena>,
+    time: Res<Time>,
+) {
+    if !modifiers.power_ups {
+        return;
+    }
+    if !spawner.0.tick(time.delta()).just_finished() || !power_ups.is_empty() {
+        return;
+    }
+    let pos = Vec2::new(
+        rng.random_range(arena.left() / 2.0..=arena.right() / 2.0),
+        rng.random_range(BOTTOM_WALL + 100.0..=TOP_WALL - 100.0),
+    );
+    commands.spawn((
+        StateScoped(InMatch),
+        PowerUp,
+        Sprite::from_color(Color::srgb(0.9, 0.8, 0.2), Vec2::ONE),
+        Transform {
+            translation: pos.extend(0.5),
+            rotation: Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            scale: Vec3::new(POWER_UP_SIZE, POWER_UP_SIZE, 1.0),
+        },
+    ));
+}
+
+// 拾取后遮暗对手的半场；没人击过球时道具直接消失
+fn collect_power_ups(
+    mut commands: Commands,
+    last_touch: Res<LastTouch>,
+    accessibility: Res<Accessibility>,
+    ball_query: Single<&Transform, With<Ball>>,
+    power_ups: Query<(Entity, &Transform), With<PowerUp>>,
+    mut blackouts: Query<&mut Blackout>,
+    arena: Res<Arena>,
+) {
+    let ball = BoundingCircle::new(ball_query.translation.truncate(), BALL_SIZE / 2.);
+    for (entity, transform) in &power_ups {
+        let power_up = BoundingCircle::new(transform.translation.truncate(), POWER_UP_SIZE / 2.);
+        if !ball.intersects(&power_up) {
+            continue;
+        }
+        commands.entity(entity).despawn();
+
+        let Some(collector) = last_touch.0 else {
+            continue;
+        };
+        let side = match collector {
+            PaddleType::Left => PaddleType::Right,
+            PaddleType::Right => PaddleType::Left,
+        };
+        // 同一侧已经遮暗时只重新计时
+        if let Some(mut blackout) = blackouts.iter_mut().find(|blackout| blackout.side == side) {
+            blackout.timer.reset();
+            continue;
+        }
+        // 减少动态效果时遮罩更淡，不至于完全看不见
+        let alpha = if accessibility.reduce_motion { 0.5 } else { 0.85 };
+        commands.spawn((
+            StateScoped(InMatch),
+            Blackout {
+                side,
+                timer: Timer::from_seconds(BLACKOUT_SECS, TimerMode::Once),
+            },
+            Sprite::from_color(Color::srgba(0.0, 0.0, 0.0, alpha), Vec2::ONE),
+            Transform {
+                translation: Vec3::new(side.side() * arena.right() / 2.0, 0.0, 2.0),
+                scale: Vec3::new(arena.right(), TOP_WALL - BOTTOM_WALL, 1.0),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn expire_blackouts(
+    mut commands: Commands,
+    mut blackouts: Query<(Entity, &mut Blackout)>,
+    time: Res<Time>,
+) {
+    for (entity, mut blackout) in &mut blackouts {
+        if blackout.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+// 高温阈值跟随本局实际的最大球速
+fn update_hot_ball(
+    mut commands: Commands,
+    mut hot_ball: ResMut<HotBall>,
+    modifiers: Res<Modifiers>,
+    warning_tone: Res<WarningTone>,
+    ball_query: Single<(&Velocity, &mut Sprite), With<Ball>>,
+    mut glow_query: Query<&mut Visibility, With<HotGlow>>,
+) {
+    let (ball_velocity, mut ball_sprite) = ball_query.into_inner();
+    let hot = ball_velocity.length() >= modifiers.ball.max_speed * HOT_BALL_FRACTION;
+    if hot == hot_ball.0 {
+        return;
+    }
+    hot_ball.0 = hot;
+
+    if hot {
+        commands.spawn((
+            AudioPlayer(warning_tone.clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(0.3)),
+        ));
+    }
+    ball_sprite.color = if hot { HOT_BALL_COLOR } else { Color::WHITE };
+    for mut visibility in &mut glow_query {
+        *visibility = if hot { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+// 发球后不久按退格键叫 let：不计分，按原样重新发球
+fn call_let(
+    mut commands: Commands,
+    let_request: Res<LetRequest>,
+    mut lets: ResMut<Lets>,
+    mut serve: ResMut<Serve>,
+    serve_timer: Res<ServeTimer>,
+    ball_query: Single<(&mut Velocity, &mut Spin, &mut Transform), With<Ball>>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    time: Res<Time>,
+) {
+    // 从球真正发出时开始计时
+    if serve_timer.counting_down() {
+        return;
+    }
+    serve.elapsed += time.delta_secs();
+    if !let_request.0 || serve.elapsed > LET_WINDOW_SECS || lets.remaining == 0 {
+        return;
+    }
+    lets.remaining -= 1;
+
+    let (mut ball_velocity, mut ball_spin, mut ball_transform) = ball_query.into_inner();
+    **ball_velocity = serve.velocity;
+    ball_spin.0 = 0.0;
+    ball_transform.translation = serve.position;
+    *serve = Serve::new(serve.position, serve.velocity);
+
     commands.spawn((
-        StateScoped(GameState::GameOver),
-        VictoryText,
+        StateScoped(InMatch),
+        BounceMessage(Timer::from_seconds(BOUNCE_MESSAGE_SECS, TimerMode::Once)),
+        Text::new(locale.text(TextKey::Let).replace("{}", &lets.remaining.to_string())),
+        TextFont {
+            font: asset_server.load(locale.font()),
+            font_size: BOUNCE_MESSAGE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        TextLayout::new_with_justify(JustifyText::Center),
         Node {
             position_type: PositionType::Absolute,
-            left: Val::Px(0.0),
-            right: Val::Px(0.0),
-            top: Val::Px(0.0),
-            bottom: Val::Px(0.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
-            flex_direction: FlexDirection::Column,
+            top: Val::Px(220.0),
+            width: Val::Percent(100.0),
             ..default()
         },
-        children![
-            (
-                Text::new(message.to_string()),
-                TextFont {
-                    font: victory_font.clone(),
-                    font_size: VICTORY_TEXT_FONT_SIZE,
-                    ..default()
-                },
-                TextColor(Color::WHITE),
-            ),
-            (
-                Text::new("PRESS K TO RESTART"),
-                TextFont {
-                    font: victory_font.clone(),
-                    font_size: HINT_FONT_SIZE,
-                    ..default()
-                },
-                TextColor(Color::WHITE),
-            ),
-        ],
     ));
 }
 
-fn game_over_keyboard(
-    mut next_state: ResMut<NextState<GameState>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::KeyK) {
-        next_state.set(GameState::Playing);
-    }
+// 子实体的坐标和缩放都相对于挡板
+fn boost_effect(effect: BoostEffect) -> impl Bundle {
+    let (color, scale, z) = match effect {
+        BoostEffect::Outline => (BOOST_COLOR, Vec3::new(1.5, 1.08, 1.0), -0.1),
+        BoostEffect::Streak => (BOOST_COLOR.with_alpha(0.35), Vec3::new(0.6, 0.0, 1.0), -0.2),
+    };
+    (
+        effect,
+        Sprite::from_color(color, Vec2::ONE),
+        Transform {
+            translation: Vec3::new(0.0, 0.0, z),
+            scale,
+            ..default()
+        },
+        Visibility::Hidden,
+    )
 }
 
-fn game_reset(
-    mut score: ResMut<Score>,
-    ball_query: Single<(&mut Velocity, &mut Transform), (With<Ball>, Without<Paddle>)>,
-    mut paddle_query: Query<&mut Transform, (With<Paddle>, Without<Ball>)>,
-    commands: Commands,
+// 描边在按住加速键时常亮；尾迹随挡板速度伸长
+fn update_boost_effects(
+    paddle_input: Res<PaddleInput>,
+    accessibility: Res<Accessibility>,
+    paddle_query: Query<(&PaddleType, &PaddleVelocity), With<Paddle>>,
+    mut effect_query: Query<(&BoostEffect, &ChildOf, &mut Visibility, &mut Transform)>,
 ) {
-    // 重置分数   
-    score.0 = 0;
-    score.1 = 0;
+    for (effect, child_of, mut visibility, mut transform) in &mut effect_query {
+        let Ok((paddle_type, paddle_velocity)) = paddle_query.get(child_of.parent()) else {
+            continue;
+        };
+        let boosting = match paddle_type {
+            PaddleType::Left => paddle_input.left.boost,
+            PaddleType::Right => paddle_input.right.boost,
+        };
+        let visible = match effect {
+            BoostEffect::Outline => boosting,
+            BoostEffect::Streak => {
+                boosting && !accessibility.reduce_motion && paddle_velocity.0 != 0.0
+            }
+        };
+        *visibility = if visible { Visibility::Inherited } else { Visibility::Hidden };
 
-    // 重置挡板位置
-    for mut paddle_transform in paddle_query.iter_mut(){
-        paddle_transform.translation.y = 0.0;
+        if *effect == BoostEffect::Streak && visible {
+            let length = 0.6 * (paddle_velocity.0.abs() / (PADDLE_SPEED * 2.0)).min(1.0);
+            transform.scale.y = length;
+            transform.translation.y = -paddle_velocity.0.signum() * (0.5 + length / 2.0);
+        }
     }
+}
 
-    // 重置小球位置、速度、发球角度
-    let (mut ball_velocity, mut ball_transform) = ball_query.into_inner();
-    **ball_velocity = INITIAL_BALL_DIRECTION.normalize() * BALL_SPEED;
-    ball_transform.translation = BALL_STARTING_POSITION;
-
-    spawn_obstacles(commands);
+// 幽灵障碍的外框忽明忽暗；减少动态效果时保持半透明
+fn flicker_ghost_obstacles(
+    accessibility: Res<Accessibility>,
+    mut ghost_query: Query<&mut Sprite, With<PhaseChance>>,
+    time: Res<Time>,
+) {
+    let alpha = if accessibility.reduce_motion {
+        0.5
+    } else {
+        0.45 + 0.3 * (time.elapsed_secs() * 6.0).sin()
+    };
+    for mut sprite in &mut ghost_query {
+        sprite.color.set_alpha(alpha);
+    }
 }
 
-fn spawn_obstacles(
+// F12：清空场景中所有实体并重新执行 setup，用于从异常状态中恢复。
+// 开局生成的实体（障碍等）不在 setup 里，因此回到主菜单重新开始
+#[cfg(feature = "dev")]
+fn rebuild_world(
     mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    root_entities: Query<Entity, (Or<(With<Transform>, With<Node>)>, Without<ChildOf>)>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    // 生成障碍
-    let mut rng = rand::rng();
-    let mut placed_obstacles: Vec<(Vec2, Vec2)> = Vec::new(); // 存储已放置的障碍(中心, 半尺寸)
-    let try_area = (
-        LEFT_WALL + 100.0,
-        RIGHT_WALL - 100.0,
-        BOTTOM_WALL + 100.0,
-        TOP_WALL - 100.0,
-    );
+    if !keyboard_input.just_pressed(KeyCode::F12) {
+        return;
+    }
+    warn!("rebuilding the game world");
+    respawn_world(&mut commands, &root_entities);
+    // 已经在主菜单时不会触发 OnEnter，直接重新显示菜单
+    if *state.get() == GameState::MainMenu {
+        commands.run_system_cached(display_main_menu);
+    } else {
+        next_state.set(GameState::MainMenu);
+    }
+}
 
-    for _ in 0..OBSTACLE_COUNT {
-        for _ in 0..50 { // 最多尝试50次
-            let mut size = Vec2::new(
-                rng.random_range(OBSTACLE_SIZE_RANGE[0].x..=OBSTACLE_SIZE_RANGE[0].y),
-                rng.random_range(OBSTACLE_SIZE_RANGE[1].x..=OBSTACLE_SIZE_RANGE[1].y),
-            );
-            // 随机交换障碍的长和宽
-            if rng.random_bool(0.5) {
-                std::mem::swap(&mut size.x, &mut size.y);
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
 
-            let half = size / 2.0;
-            let pos = Vec2::new(
-                rng.random_range(try_area.0 + half.x..=try_area.1 - half.x),
-                rng.random_range(try_area.2 + half.y..=try_area.3 - half.y),
-            );
+    fn unit_box() -> Aabb2d {
+        Aabb2d::new(Vec2::ZERO, Vec2::splat(50.0))
+    }
 
-            // 检查是否与已放置的障碍重叠
-            let mut overlap = false;
-            for (other_pos, other_half) in &placed_obstacles {
-                if (pos.x - other_pos.x).abs() < (half.x + other_half.x + 10.0) &&
-                   (pos.y - other_pos.y).abs() < (half.y + other_half.y + 10.0) {
-                    overlap = true;
-                    break;
-                }
-            }
-            // 检查是否与中间区域(计分板、地图中线)重叠
-            if pos.x.abs() < half.x + 150.0 {
-                overlap = true;
-            }
+    #[test]
+    fn ball_collision_detects_each_side() {
+        let radius = BALL_SIZE / 2.0;
+        let cases = [
+            (Vec2::new(-55.0, 0.0), Collision::Left),
+            (Vec2::new(55.0, 0.0), Collision::Right),
+            (Vec2::new(0.0, 55.0), Collision::Top),
+            (Vec2::new(0.0, -55.0), Collision::Bottom),
+        ];
+        for (center, expected) in cases {
+            let ball = BoundingCircle::new(center, radius);
+            assert_eq!(ball_collision(ball, unit_box()), Some(expected), "ball at {center}");
+        }
+    }
 
-            if !overlap {
-                placed_obstacles.push((pos, half));
-                commands.spawn((
-                    StateScoped(GameState::Playing), // 进入Playing重新生成，退出自动销毁
-                    Sprite::from_color(Color::WHITE, Vec2::ONE),
-                    Transform {
-                        translation: pos.extend(0.0),
-                        scale: Vec3::new(size.x, size.y, 1.0),
-                        ..default()
-                    },
-                    Obstacle,
-                    Collider,
-                ));
-                commands.spawn((
-                    StateScoped(GameState::Playing),
-                    Sprite::from_color(Color::BLACK, Vec2::ONE),
-                    Transform {
-                        translation: pos.extend(0.0),
-                        scale: Vec3::new(size.x - 10.0, size.y - 10.0, 1.0),
-                        ..default()
-                    },
-                    Obstacle,
-                    Collider,
-                ));
-                break;
+    #[test]
+    fn ball_collision_without_overlap_is_none() {
+        let ball = BoundingCircle::new(Vec2::new(100.0, 0.0), BALL_SIZE / 2.0);
+        assert_eq!(ball_collision(ball, unit_box()), None);
+    }
+
+    #[test]
+    fn ball_collision_corner_tie_resolves_vertically() {
+        let radius = BALL_SIZE / 2.0;
+        let top = BoundingCircle::new(Vec2::new(55.0, 55.0), radius);
+        assert_eq!(ball_collision(top, unit_box()), Some(Collision::Top));
+        let bottom = BoundingCircle::new(Vec2::new(-55.0, -55.0), radius);
+        assert_eq!(ball_collision(bottom, unit_box()), Some(Collision::Bottom));
+    }
+
+    // 边长 100 的正方形障碍旋转 45°，成为一个菱形
+    fn diamond() -> (Isometry2d, Vec2) {
+        (Isometry2d::new(Vec2::ZERO, Rot2::degrees(45.0)), Vec2::splat(50.0))
+    }
+
+    #[test]
+    fn oriented_collision_hits_slanted_face() {
+        let (isometry, half_size) = diamond();
+        // 右上斜面的法线为 (1, 1) 方向，面到中心的距离为 50
+        let normal = Vec2::new(1.0, 1.0).normalize();
+        let ball = BoundingCircle::new(normal * (50.0 + BALL_SIZE / 2.0 - 2.0), BALL_SIZE / 2.0);
+        let (collision, contact) = ball_collision_oriented(ball, isometry, half_size).expect("should hit");
+        let Collision::Oriented(hit_normal) = collision else {
+            panic!("expected an oriented collision, got {collision:?}");
+        };
+        assert!(hit_normal.abs_diff_eq(normal, 1e-4), "{hit_normal}");
+        assert!(contact.abs_diff_eq(normal * 50.0, 1e-3), "{contact}");
+    }
+
+    #[test]
+    fn oriented_collision_misses_inside_unrotated_bounds() {
+        let (isometry, half_size) = diamond();
+        // 菱形的轴对齐包围盒半宽约 70.7；(55, 55) 在包围盒内，却在斜面之外
+        let ball = BoundingCircle::new(Vec2::new(55.0, 55.0), BALL_SIZE / 2.0);
+        let bounds = Rectangle::from_size(half_size * 2.0).aabb_2d(isometry);
+        assert!(ball.intersects(&bounds));
+        assert_eq!(ball_collision_oriented(ball, isometry, half_size), None);
+
+        let transform = Transform::from_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4))
+            .with_scale(Vec3::new(100.0, 100.0, 1.0));
+        assert_eq!(collider_collision(ball, &transform), None);
+    }
+
+    #[test]
+    fn scoreboard_shows_multi_digit_scores_beside_the_center_line() {
+        // TextUiWriter 依赖 TextPlugin 注册的资源
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), ImagePlugin::default(), bevy::text::TextPlugin));
+        let world = app.world_mut();
+        world.init_resource::<Modifiers>();
+        world.init_resource::<ScorePop>();
+        world.init_resource::<Accessibility>();
+        let boards = [PaddleType::Left, PaddleType::Right].map(|side| {
+            let board = (Text::new(""), ScoreboardUi(side), scoreboard_node(side), Transform::default());
+            world.spawn((board, children![TextSpan::default()])).id()
+        });
+
+        for (left, right) in [(10, 99), (99, 100), (100, 10)] {
+            world.insert_resource(Score(left, right));
+            world.run_system_once(update_scoreboard).unwrap();
+            for (board, expected) in boards.into_iter().zip([left, right]) {
+                let span = world.entity(board).get::<Children>().unwrap()[0];
+                assert_eq!(**world.entity(span).get::<TextSpan>().unwrap(), expected.to_string());
             }
         }
+
+        // 两侧都锚在中线上，另一侧的位置留给文本宽度自由增长
+        let left = world.entity(boards[0]).get::<Node>().unwrap();
+        assert_eq!((left.right, left.left), (Val::Percent(50.0), Val::Auto));
+        assert_eq!(left.margin.right, Val::Px(SCOREBOARD_CENTER_GAP));
+        let right = world.entity(boards[1]).get::<Node>().unwrap();
+        assert_eq!((right.left, right.right), (Val::Percent(50.0), Val::Auto));
+        assert_eq!(right.margin.left, Val::Px(SCOREBOARD_CENTER_GAP));
+    }
+
+    #[test]
+    fn paddle_bounce_near_top_sends_ball_upward() {
+        let paddle = Transform::from_xyz(-600.0, 0.0, 0.0).with_scale(PADDLE_SIZE.extend(1.0));
+        let top_hit = PADDLE_SIZE.y / 2.0 - 5.0;
+        let velocity = paddle_bounce_velocity(PaddleType::Left, &paddle, top_hit, BALL_SPEED);
+        assert!(velocity.y > 0.0, "{velocity}");
+        assert!(velocity.x > 0.0, "{velocity}");
+        assert!((velocity.length() - BALL_SPEED).abs() < 1e-3);
+
+        // 放大后的挡板：同样的击球点离边缘更远，角度应更小
+        let grown = paddle.with_scale((PADDLE_SIZE * Vec2::new(1.0, 2.0)).extend(1.0));
+        let grown_velocity = paddle_bounce_velocity(PaddleType::Left, &grown, top_hit, BALL_SPEED);
+        assert!(grown_velocity.y > 0.0 && grown_velocity.y < velocity.y);
+    }
+
+    #[test]
+    fn mouse_target_moves_at_most_max_step() {
+        let mouse = |target| PaddleCommand {
+            direction: 0.0,
+            boost: false,
+            block: false,
+            target: Some(target),
+        };
+        let max_step = PADDLE_SPEED / 64.0;
+        assert_eq!(next_paddle_position(0.0, &mouse(300.0), max_step), max_step);
+        assert_eq!(next_paddle_position(0.0, &mouse(-300.0), max_step), -max_step);
+        assert_eq!(next_paddle_position(0.0, &mouse(2.0), max_step), 2.0);
+    }
+
+    #[test]
+    fn repeated_speed_ups_never_exceed_max_speed() {
+        let ball = BallDynamics::default();
+        assert_eq!(ball.max_speed, MAX_BALL_SPEED);
+        let mut velocity = Vec2::new(0.6, 0.8) * BALL_SPEED;
+        for _ in 0..50 {
+            velocity = sped_up_velocity(velocity, &ball);
+            assert!(velocity.length() <= MAX_BALL_SPEED + 1e-3, "{}", velocity.length());
+        }
+        assert!((velocity.length() - MAX_BALL_SPEED).abs() < 1e-3);
+        assert!(velocity.normalize().abs_diff_eq(Vec2::new(0.6, 0.8), 1e-5));
+    }
+
+    fn first_to(target_score: usize) -> Modifiers {
+        Modifiers {
+            target_score,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn third_point_wins_first_to_three() {
+        let modifiers = first_to(3);
+        assert!(!wins_match(2, 1, &modifiers));
+        assert!(wins_match(3, 1, &modifiers));
+        assert!(wins_match(3, 2, &modifiers));
+        assert!(!wins_match(3, 1, &Modifiers { endless: true, ..first_to(3) }));
+    }
+
+    #[test]
+    fn win_by_two_needs_a_two_point_lead() {
+        let modifiers = Modifiers {
+            win_by_two: true,
+            ..first_to(9)
+        };
+        assert!(!wins_match(9, 8, &modifiers));
+        assert!(wins_match(10, 8, &modifiers));
+        assert!(!wins_match(11, 10, &modifiers));
+        assert!(wins_match(12, 10, &modifiers));
+        // 关闭规则时 9-8 直接结束
+        assert!(wins_match(9, 8, &first_to(9)));
+    }
+
+    #[test]
+    fn award_point_moves_to_win_freeze_with_winner() {
+        let mut world = World::new();
+        world.insert_resource(first_to(3));
+        world.insert_resource(Score(0, 1));
+        world.init_resource::<Health>();
+        world.init_resource::<Winner>();
+        world.init_resource::<NextState<GameState>>();
+        world.init_resource::<Events<ScoreEvent>>();
+
+        let score_right = |modifiers: Res<Modifiers>,
+                           mut score: ResMut<Score>,
+                           mut health: ResMut<Health>,
+                           mut winner: ResMut<Winner>,
+                           mut next_state: ResMut<NextState<GameState>>,
+                           mut score_events: EventWriter<ScoreEvent>| {
+            award_point(PaddleType::Right, &modifiers, &mut score, &mut health, &mut winner, &mut next_state, &mut score_events);
+        };
+        world.run_system_once(score_right).unwrap();
+        assert_eq!(world.resource::<Winner>().0, None);
+        world.run_system_once(score_right).unwrap();
+        assert_eq!(world.resource::<Winner>().0, Some(PaddleType::Right));
+        assert!(matches!(world.resource::<NextState<GameState>>(), NextState::Pending(GameState::WinFreeze)));
+        assert_eq!(world.resource::<Score>().1, 3);
+    }
+
+    #[test]
+    fn single_player_hands_only_the_right_paddle_to_the_ai() {
+        let mut world = World::new();
+        world.insert_resource(Modifiers {
+            ai_opponent: true,
+            ..default()
+        });
+        let left = world.spawn((Paddle, PaddleType::Left)).id();
+        let right = world.spawn((Paddle, PaddleType::Right)).id();
+        world.run_system_once(assign_ai_paddle).unwrap();
+        assert!(!world.entity(left).contains::<AiControlled>());
+        assert!(world.entity(right).contains::<AiControlled>());
+
+        world.resource_mut::<Modifiers>().ai_opponent = false;
+        world.run_system_once(assign_ai_paddle).unwrap();
+        assert!(!world.entity(right).contains::<AiControlled>());
+    }
+
+    // ball_reset 所需的最小世界：按固定种子播种的比赛随机数和一个主球
+    fn serve_world(seed: u64) -> World {
+        let mut world = World::new();
+        world.insert_resource(GameRng(StdRng::seed_from_u64(seed)));
+        world.init_resource::<Arena>();
+        world.init_resource::<Serve>();
+        world.init_resource::<ServeTimer>();
+        world.init_resource::<NextState<GameState>>();
+        world.init_resource::<Events<ScoreEvent>>();
+        world.spawn((Ball, Velocity(Vec2::ZERO), Spin::default(), Transform::default()));
+        world
+    }
+
+    // 发出得分事件后运行一次 ball_reset，返回主球的速度和位置
+    fn run_ball_reset(world: &mut World, events: impl IntoIterator<Item = ScoreEvent>) -> (Vec2, Vec3) {
+        for event in events {
+            world.send_event(event);
+        }
+        world.run_system_once(ball_reset).unwrap();
+        let mut balls = world.query_filtered::<(&Velocity, &Transform), With<Ball>>();
+        let (velocity, transform) = balls.single(world).unwrap();
+        (**velocity, transform.translation)
+    }
+
+
+    #[test]
+    fn serve_position_follows_arena_width() {
+        let classic = Arena::default();
+        let widescreen = Arena { aspect: ArenaAspect::Widescreen };
+        assert_eq!(serve_position(PaddleType::Right, &classic).x, classic.left() + 40.0);
+        assert_eq!(serve_position(PaddleType::Right, &widescreen).x, widescreen.left() + 40.0);
+        assert!(serve_position(PaddleType::Right, &widescreen).x < serve_position(PaddleType::Right, &classic).x);
+    }
+
+    #[test]
+    fn ball_reset_serves_toward_the_player_who_conceded() {
+        let arena = Arena::default();
+        for (event, toward) in [
+            (ScoreEvent::Player1Scored, PaddleType::Right),
+            (ScoreEvent::Player2Scored, PaddleType::Left),
+        ] {
+            let mut world = serve_world(0);
+            let (velocity, translation) = run_ball_reset(&mut world, [event]);
+            // 球从失分方的对面出发，朝失分方飞去
+            assert_eq!(velocity.x.signum(), toward.side());
+            assert_eq!(translation.truncate(), serve_position(toward, &arena));
+        }
+    }
+
+    #[test]
+    fn ball_reset_follows_the_last_score_event() {
+        let mut world = serve_world(0);
+        let (velocity, _) = run_ball_reset(&mut world, [ScoreEvent::Player2Scored, ScoreEvent::Player1Scored]);
+        assert!(velocity.x > 0.0, "{velocity}");
+    }
+
+    #[test]
+    fn ball_reset_skips_the_deciding_point() {
+        let mut world = serve_world(0);
+        world.resource_mut::<NextState<GameState>>().set(GameState::WinFreeze);
+        let (velocity, translation) = run_ball_reset(&mut world, [ScoreEvent::Player1Scored]);
+        assert_eq!((velocity, translation), (Vec2::ZERO, Vec3::ZERO));
+        assert!(!world.resource::<ServeTimer>().counting_down());
+    }
+
+
+    #[test]
+    fn pinned_seed_gives_pinned_serve_velocity() {
+        let mut world = serve_world(42);
+        let (velocity, _) = run_ball_reset(&mut world, [ScoreEvent::Player1Scored]);
+        assert_eq!(velocity, Vec2::new(392.2703, -78.25609));
+        assert!(world.resource::<ServeTimer>().counting_down());
+
+        // 第二次运行同一种子得到同一个发球
+        let mut again = serve_world(42);
+        assert_eq!(run_ball_reset(&mut again, [ScoreEvent::Player1Scored]).0, velocity);
+    }
+
+
+    // 与压力测试规模相当的场景：几百个随机大小、部分旋转的碰撞体
+    fn random_colliders(count: usize) -> Vec<Transform> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..count)
+            .map(|_| {
+                let position = Vec3::new(rng.random_range(-640.0..640.0), rng.random_range(-300.0..300.0), 0.0);
+                let size = Vec3::new(rng.random_range(5.0..60.0), rng.random_range(5.0..60.0), 1.0);
+                let angle = if rng.random_bool(0.3) { rng.random_range(0.0..std::f32::consts::PI) } else { 0.0 };
+                Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_z(angle))
+                    .with_scale(size)
+            })
+            .collect()
+    }
+
+    fn collider_grid(colliders: &[Transform]) -> ColliderGrid {
+        let mut grid = ColliderGrid::default();
+        grid.rebuild(
+            colliders
+                .iter()
+                .enumerate()
+                .map(|(index, transform)| (Entity::from_raw(index as u32), collider_bounds(transform))),
+        );
+        grid
+    }
+
+    fn random_balls(count: usize) -> Vec<BoundingCircle> {
+        let mut rng = StdRng::seed_from_u64(11);
+        (0..count)
+            .map(|_| {
+                let center = Vec2::new(rng.random_range(-640.0..640.0), rng.random_range(-300.0..300.0));
+                BoundingCircle::new(center, BALL_SIZE / 2.0)
+            })
+            .collect()
+    }
+
+    // 不用网格，逐个检测全部碰撞体
+    fn naive_hits(balls: &[BoundingCircle], colliders: &[Transform]) -> Vec<Vec<usize>> {
+        balls
+            .iter()
+            .map(|ball| {
+                (0..colliders.len())
+                    .filter(|&index| collider_collision(*ball, &colliders[index]).is_some())
+                    .collect()
+            })
+            .collect()
+    }
+
+    // 先用网格筛出候选再检测，同时返回候选总数
+    fn grid_hits(balls: &[BoundingCircle], colliders: &[Transform], grid: &ColliderGrid) -> (Vec<Vec<usize>>, usize) {
+        let mut candidates = 0;
+        let hits = balls
+            .iter()
+            .map(|ball| {
+                let nearby = grid.nearby(Aabb2d::new(ball.center(), Vec2::splat(ball.radius())));
+                candidates += nearby.len();
+                nearby
+                    .into_iter()
+                    .map(|entity| entity.index() as usize)
+                    .filter(|&index| collider_collision(*ball, &colliders[index]).is_some())
+                    .collect()
+            })
+            .collect();
+        (hits, candidates)
+    }
+
+    #[test]
+    fn collider_grid_finds_same_hits_as_naive_loop() {
+        let colliders = random_colliders(500);
+        let balls = random_balls(2000);
+        let naive = naive_hits(&balls, &colliders);
+        let (gridded, grid_candidates) = grid_hits(&balls, &colliders, &collider_grid(&colliders));
+
+        assert_eq!(naive, gridded);
+        assert!(naive.iter().any(|hits| !hits.is_empty()));
+        // 宽相位应当排除绝大多数碰撞体
+        let naive_candidates = balls.len() * colliders.len();
+        assert!(grid_candidates * 10 < naive_candidates, "{grid_candidates} of {naive_candidates}");
+    }
+
+    // 网格与逐个检测的耗时对比，计时受机器负载影响，不随普通测试运行：
+    // cargo test --release -- --ignored collider_grid_benchmark
+    #[test]
+    #[ignore]
+    fn collider_grid_benchmark() {
+        let colliders = random_colliders(500);
+        let balls = random_balls(2000);
+        let grid = collider_grid(&colliders);
+        let rounds = 20;
+
+        let naive_start = Instant::now();
+        for _ in 0..rounds {
+            std::hint::black_box(naive_hits(&balls, &colliders));
+        }
+        let naive_time = naive_start.elapsed() / rounds;
+
+        let grid_start = Instant::now();
+        for _ in 0..rounds {
+            std::hint::black_box(grid_hits(&balls, &colliders, &grid));
+        }
+        let grid_time = grid_start.elapsed() / rounds;
+
+        assert!(grid_time < naive_time, "grid {grid_time:?} per round, naive {naive_time:?} per round");
+    }
+
+    #[test]
+    fn fast_ball_hits_thin_obstacle_instead_of_tunnelling() {
+        let radius = BALL_SIZE / 2.0;
+        let obstacle = Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::new(6.0, 120.0, 1.0));
+        // 一个固定帧内移动 400，远大于障碍厚度
+        let (previous, current) = (Vec2::new(-200.0, 10.0), Vec2::new(200.0, 10.0));
+        assert!(collider_collision(BoundingCircle::new(current, radius), &obstacle).is_none());
+
+        let contact = swept_contact(previous, current, radius, [&obstacle]).expect("sweep should hit");
+        assert!(contact.x < 0.0, "{contact}");
+        let hit = collider_collision(BoundingCircle::new(contact, radius), &obstacle);
+        assert_eq!(hit.map(|(collision, _)| collision), Some(Collision::Left));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fast_ball_hits_rotated_thin_obstacle() {
+        let radius = BALL_SIZE / 2.0;
+        let obstacle = Transform::from_xyz(0.0, 0.0, 0.0)
+            .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4))
+            .with_scale(Vec3::new(6.0, 120.0, 1.0));
+        let (previous, current) = (Vec2::new(-200.0, 0.0), Vec2::new(200.0, 0.0));
+        assert!(collider_collision(BoundingCircle::new(current, radius), &obstacle).is_none());
+
+        let contact = swept_contact(previous, current, radius, [&obstacle]).expect("sweep should hit");
+        let hit = collider_collision(BoundingCircle::new(contact, radius), &obstacle);
+        assert!(matches!(hit, Some((Collision::Oriented(normal), _)) if normal.x < 0.0), "{hit:?}");
+    }
+
+    #[test]
+    fn sweep_ignores_colliders_already_touching_at_start() {
+        let radius = BALL_SIZE / 2.0;
+        let obstacle = Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::new(6.0, 120.0, 1.0));
+        let (previous, current) = (Vec2::new(-5.0, 0.0), Vec2::new(200.0, 0.0));
+        assert_eq!(swept_contact(previous, current, radius, [&obstacle]), None);
+    }
+
+    #[test]
+    fn language_toggle_only_offers_locales_whose_font_ships() {
+        assert!(Locale::English.available());
+        assert_eq!(Locale::English.next() == Locale::Chinese, Locale::Chinese.available());
+    }
+}